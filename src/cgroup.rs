@@ -3,12 +3,18 @@ use std::{
         self,
         OpenOptions,
     },
-    io::Write,
-    path::{
-        Path,
-        PathBuf,
+    io::{
+        self,
+        Write,
     },
+    os::unix::fs::MetadataExt,
+    path::Path,
     str::FromStr,
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 use crate::{controller::ControllerType, io::IO, error::{
@@ -18,24 +24,59 @@ use crate::{controller::ControllerType, io::IO, error::{
         read_file_into_string,
         read_newline_separated_values,
         read_space_separated_values,
+        write_single_value,
     }};
 use crate::cpu::Cpu;
+use crate::cpuset::Cpuset;
+use crate::fd::CGroupFd;
+use crate::hugetlb::HugeTlb;
 use crate::memory::Memory;
-
-pub struct CGroup<'a> {
-    path: &'a Path
+use crate::misc::Misc;
+use crate::pids::Pids;
+use crate::rdma::Rdma;
+use crate::psi::IRQPressure;
+use crate::walk::{self, Descendants};
+use crate::watch::EventWatcher;
+
+/// Upper bound on how often [`CGroup::migrate_all_to`] re-reads cgroup.procs.
+const MIGRATE_ROUNDS: u32 = 100;
+/// How long to wait for a cgroup to report `frozen 1` before working on it.
+const FREEZE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An owned handle to a cgroup directory. Cloning is cheap, and handles can be shared between
+/// threads.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CGroup {
+    path: Arc<Path>
 }
 
-impl<'a> CGroup<'a> {
-    pub fn new(path: &'a Path) -> Self {
+impl CGroup {
+    pub fn new<P: Into<Arc<Path>>>(path: P) -> Self {
         CGroup {
-            path
+            path: path.into()
         }
     }
 
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    ///The cgroup ID, i.e. the inode number of the cgroup directory
+    pub fn id(&self) -> Result<u64> {
+        let meta = fs::metadata(&self.path)
+            .map_err(|e| CGroupError::FSErr(e.kind()))?;
+        Ok(meta.ino())
+    }
+
+    ///Pin this cgroup with a directory fd, so that later accesses cannot race with the cgroup
+    ///being removed and recreated under the same path.
+    pub fn open_fd(&self) -> Result<CGroupFd> {
+        CGroupFd::open(&self.path)
+    }
+
     ///cgroup.controllers
     pub fn controllers(&self) -> Result<Vec<ControllerType>> {
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.controllers");
         let content = read_file_into_string(path.as_path())?;
         Ok(read_space_separated_values(content))
@@ -43,7 +84,7 @@ impl<'a> CGroup<'a> {
 
     ///cgroup.subtree_control
     pub fn subtree_control(&self) -> Result<Vec<ControllerType>> {
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.subtree_control");
         let content = read_file_into_string(path.as_path())?;
         Ok(read_space_separated_values(content))
@@ -53,29 +94,8 @@ impl<'a> CGroup<'a> {
                                enables: Vec<ControllerType>,
                                disables: Option<Vec<ControllerType>>,
     ) -> Result<()> {
-        let mut line: String = enables
-            .iter()
-            .map(|e| {
-                let mut s = e.to_string();
-                s.insert_str(0, "+");
-                return s;
-            })
-            .collect::<Vec<String>>()
-            .join(" ");
-        if let Some(disables) = disables {
-            let _line = disables
-                .iter()
-                .map(|e| {
-                    let mut s = e.to_string();
-                    s.insert_str(0, "-");
-                    return s;
-                })
-                .collect::<Vec<String>>()
-                .join(" ");
-            line.push(' ');
-            line.push_str(&_line);
-        };
-        let mut path = PathBuf::from(&self.path);
+        let line = subtree_control_line(enables, disables);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.subtree_control");
         fs::write(path.as_path(), line)
             .map_err(|e| CGroupError::FSErr(e.kind()))
@@ -83,7 +103,7 @@ impl<'a> CGroup<'a> {
 
     ///cgroup.type
     pub fn cg_type(&self) -> Result<CGroupType> {
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.type");
         let content = read_file_into_string(path.as_path())?;
         CGroupType::from_str(&content)
@@ -91,7 +111,7 @@ impl<'a> CGroup<'a> {
 
     ///cgruop.procs
     pub fn procs(&self) -> Result<Vec<i32>> {
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.procs");
         let content = read_file_into_string(path.as_path())?;
         Ok(read_newline_separated_values(content))
@@ -99,13 +119,13 @@ impl<'a> CGroup<'a> {
 
     ///cgroup.procs
     pub fn add_pid(&self, pid: u32) -> Result<()> {
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.procs");
         let mut file = OpenOptions::new()
             .append(true)
             .open(&path)
             .map_err(|err| CGroupError::FSErr(err.kind()))?;
-        match file.write(&pid.to_string().as_bytes()) {
+        match file.write(pid.to_string().as_bytes()) {
             Ok(size) => {
                 if size == 0 {
                     Err(CGroupError::WriteZeroByteErr)
@@ -121,20 +141,20 @@ impl<'a> CGroup<'a> {
 
     ///cgroup.threads
     pub fn threads(&self) -> Result<Vec<i32>> {
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.threads");
         let content = read_file_into_string(path.as_path())?;
         Ok(read_newline_separated_values(content))
     }
 
     pub fn add_tid(&self, tid: u32) -> Result<()> {
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.threads");
         let mut file = OpenOptions::new()
             .append(true)
             .open(&path)
             .map_err(|err| CGroupError::FSErr(err.kind()))?;
-        match file.write(&tid.to_string().as_bytes()) {
+        match file.write(tid.to_string().as_bytes()) {
             Ok(size) => {
                 if size == 0 {
                     Err(CGroupError::WriteZeroByteErr)
@@ -148,9 +168,148 @@ impl<'a> CGroup<'a> {
         }
     }
 
+    ///Move every process of this cgroup into `target` and return how many were moved.
+    ///cgroup.procs is read again after each pass, so children forked during the move follow
+    ///their parents. Processes that exit in the meantime are skipped, and so are processes
+    ///outside our PID namespace, which cgroup.procs lists as `0`.
+    pub fn migrate_all_to(&self, target: &CGroup) -> Result<usize> {
+        let mut path = target.path.to_path_buf();
+        path.push("cgroup.procs");
+        let mut moved = 0;
+        for _ in 0..MIGRATE_ROUNDS {
+            // writing 0 would move the calling process instead
+            let procs: Vec<i32> = self.procs()?.into_iter().filter(|&pid| pid > 0).collect();
+            if procs.is_empty() {
+                return Ok(moved);
+            }
+            for pid in procs {
+                match write_pid(&path, pid) {
+                    Ok(()) => moved += 1,
+                    Err(err) if err.raw_os_error() == Some(libc::ESRCH) => {}
+                    Err(err) => return Err(CGroupError::FSErr(err.kind())),
+                }
+            }
+        }
+        Err(CGroupError::FSErr(io::ErrorKind::ResourceBusy))
+    }
+
+    ///Same as [`CGroup::migrate_all_to`], but this cgroup is frozen while processes are moved,
+    ///so that none of them can fork and escape. Migration starts once the kernel reports the
+    ///cgroup as frozen, and fails with `TimeoutErr` if that takes longer than 5s. The previous
+    ///freeze state is restored afterwards, also on errors.
+    pub fn migrate_all_to_frozen(&self, target: &CGroup) -> Result<usize> {
+        let Freeze(was_frozen) = self.freeze_state()?;
+        if !was_frozen {
+            self.freeze()?;
+        }
+        let result = self.wait_frozen(FREEZE_TIMEOUT)
+            .and_then(|()| self.migrate_all_to(target));
+        if !was_frozen {
+            let thawed = self.thaw();
+            return result.and_then(|moved| thawed.map(|()| moved));
+        }
+        result
+    }
+
+    ///Kill every process in this cgroup and its descendants through cgroup.kill, and wait until
+    ///cgroup.events reports `populated 0`. Kernels older than 5.14 have no cgroup.kill; there
+    ///the subtree is frozen, each process gets a SIGKILL once freezing has completed, and the
+    ///subtree is thawed again, also if anything fails on the way.
+    pub fn kill(&self, timeout: Duration) -> Result<()> {
+        if self.path.join("cgroup.kill").exists() {
+            write_single_value(&self.path, "cgroup.kill", 1)?;
+        } else {
+            self.kill_each()?;
+        }
+        self.wait_until_empty(timeout)
+    }
+
+    fn kill_each(&self) -> Result<()> {
+        let Freeze(was_frozen) = self.freeze_state()?;
+        self.freeze()?;
+        // nothing can fork once the subtree is frozen, so a single pass reaches every process
+        let result = self.wait_frozen(FREEZE_TIMEOUT)
+            .and_then(|()| self.kill_frozen());
+        if !was_frozen {
+            let thawed = self.thaw();
+            return result.and(thawed);
+        }
+        result
+    }
+
+    fn kill_frozen(&self) -> Result<()> {
+        let mut c_groups = vec![self.clone()];
+        for entry in self.descendants() {
+            c_groups.push(entry?.cgroup());
+        }
+        for c_group in c_groups {
+            let procs = match c_group.procs() {
+                Ok(procs) => procs,
+                Err(CGroupError::FSErr(io::ErrorKind::NotFound)) => continue,
+                Err(err) => return Err(err),
+            };
+            for pid in procs {
+                // 0 stands for a process outside our PID namespace, and kill(0) would hit our
+                // own process group
+                if pid <= 0 || pid as u32 == std::process::id() {
+                    continue;
+                }
+                if unsafe { libc::kill(pid, libc::SIGKILL) } != 0 {
+                    let err = io::Error::last_os_error();
+                    // processes that are already gone
+                    if err.raw_os_error() != Some(libc::ESRCH) {
+                        return Err(CGroupError::FSErr(err.kind()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///Wait until cgroup.events reports `populated 0`, i.e. no process is left in this cgroup or
+    ///any of its descendants.
+    pub fn wait_until_empty(&self, timeout: Duration) -> Result<()> {
+        self.wait_events(timeout, true, |events| !events.populated)
+    }
+
+    ///Wait for cgroup.events notifications until `done` holds. A cgroup that is missing or
+    ///disappears counts as done if `removed_is_done` is set, and fails with `NotFound` otherwise.
+    fn wait_events<F>(&self, timeout: Duration, removed_is_done: bool, done: F) -> Result<()>
+    where
+        F: Fn(&CGroupEvent) -> bool,
+    {
+        let removed = || if removed_is_done {
+            Ok(())
+        } else {
+            Err(CGroupError::FSErr(io::ErrorKind::NotFound))
+        };
+        let deadline = Instant::now() + timeout;
+        let mut watcher = EventWatcher::new()?;
+        match watcher.add(self) {
+            Ok(events) if done(&events) => return Ok(()),
+            Ok(_) => {}
+            Err(CGroupError::FSErr(io::ErrorKind::NotFound)) => return removed(),
+            Err(err) => return Err(err),
+        }
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let transitions = watcher.wait(Some(left))?;
+            if transitions.is_empty() {
+                return Err(CGroupError::TimeoutErr);
+            }
+            for transition in transitions {
+                match transition.new {
+                    Some(events) if done(&events) => return Ok(()),
+                    Some(_) => {}
+                    None => return removed(),
+                }
+            }
+        }
+    }
+
     ///cgroup.events
     pub fn events(&self) -> Result<CGroupEvent> {
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.events");
         let content = read_file_into_string(path.as_path())?;
         CGroupEvent::from_str(&content)
@@ -159,7 +318,7 @@ impl<'a> CGroup<'a> {
     ///cgroup.max.descendants
     pub fn max_descendants(&self) -> Result<Max> {
         let filename = "cgroup.max.descendants";
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push(filename);
         let content = read_file_into_string(path.as_path())?;
         Max::from_str(&content)
@@ -167,7 +326,7 @@ impl<'a> CGroup<'a> {
 
     ///cgroup.max.descendants
     pub fn set_max_descendants(&self, max: u32) -> Result<()> {
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.max.descendants");
         fs::write(path.as_path(), max.to_string())
             .map_err(|e| CGroupError::FSErr(e.kind()))
@@ -176,7 +335,7 @@ impl<'a> CGroup<'a> {
     ///cgroup.max.depth
     pub fn max_depth(&self) -> Result<Max> {
         let filename = "cgroup.max.depth";
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push(filename);
         let content = read_file_into_string(path.as_path())?;
         Max::from_str(&content)
@@ -184,7 +343,7 @@ impl<'a> CGroup<'a> {
 
     ///cgroup.max.depth
     pub fn set_max_depth(&self, max: u32) -> Result<()> {
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.max.depth");
         fs::write(path.as_path(), max.to_string())
             .map_err(|e| CGroupError::FSErr(e.kind()))
@@ -192,44 +351,145 @@ impl<'a> CGroup<'a> {
 
     ///cgroup.stat
     pub fn stat(&self) -> Result<CGroupStat> {
-        let mut path = PathBuf::from(&self.path);
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.stat");
         let content = read_file_into_string(path.as_path())?;
         CGroupStat::from_str(&content)
     }
 
+    ///irq.pressure, only present on kernels built with CONFIG_IRQ_TIME_ACCOUNTING
+    pub fn irq_pressure(&self) -> Result<IRQPressure> {
+        let mut path = self.path.to_path_buf();
+        path.push("irq.pressure");
+        let content = read_file_into_string(path.as_path())?;
+        IRQPressure::from_str(&content)
+    }
+
     ///cgroup.freeze
-    pub fn freeze(&self) -> Result<Freeze> {
-        let mut path = PathBuf::from(&self.path);
+    pub fn freeze_state(&self) -> Result<Freeze> {
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.freeze");
         let content = read_file_into_string(path.as_path())?;
         Freeze::from_str(&content)
     }
 
-    ///cgroup.freeze
+    ///Freeze this cgroup, same as [`CGroup::freeze`]. Kept for callers of the old API.
     pub fn set_freeze(&self) -> Result<()> {
-        let mut path = PathBuf::from(&self.path);
+        self.set_freeze_state(true)
+    }
+
+    ///cgroup.freeze
+    pub fn set_freeze_state(&self, freeze: bool) -> Result<()> {
+        let mut path = self.path.to_path_buf();
         path.push("cgroup.freeze");
-        fs::write(path.as_path(), "1")
+        fs::write(path.as_path(), if freeze { "1" } else { "0" })
             .map_err(|e| CGroupError::FSErr(e.kind()))
     }
 
+    ///Request freezing this cgroup and its descendants. Freezing completes asynchronously, see
+    ///[`CGroup::wait_frozen`].
+    pub fn freeze(&self) -> Result<()> {
+        self.set_freeze_state(true)
+    }
+
+    ///Thaw this cgroup, unless an ancestor is frozen as well.
+    pub fn thaw(&self) -> Result<()> {
+        self.set_freeze_state(false)
+    }
+
+    ///Wait until cgroup.events reports `frozen 1`. Fails with `NotFound` if the cgroup does not
+    ///exist or is removed in the meantime.
+    pub fn wait_frozen(&self, timeout: Duration) -> Result<()> {
+        self.wait_events(timeout, false, |events| events.frozen)
+    }
+
+    ///Direct child cgroups, sorted by name
+    pub fn children(&self) -> Result<Vec<CGroup>> {
+        Ok(walk::children(&self.path)?
+            .into_iter()
+            .map(CGroup::new)
+            .collect())
+    }
+
+    ///All cgroups below this one, depth first
+    pub fn descendants(&self) -> Descendants<'_> {
+        Descendants::new(&self.path)
+    }
+
     ///cpu
     pub fn cpu(&self) -> Cpu {
-        Cpu::new(self.path)
+        Cpu::new(self.path.clone())
+    }
+
+    ///cpuset
+    pub fn cpuset(&self) -> Cpuset {
+        Cpuset::new(self.path.clone())
+    }
+
+    ///hugetlb
+    pub fn hugetlb(&self) -> HugeTlb {
+        HugeTlb::new(self.path.clone())
     }
 
     ///memory
     pub fn memory(&self) -> Memory {
-        Memory::new(self.path)
+        Memory::new(self.path.clone())
     }
 
     ///io
     pub fn io(&self) -> IO {
-        IO::new(self.path)
+        IO::new(self.path.clone())
+    }
+
+    ///pids
+    pub fn pids(&self) -> Pids {
+        Pids::new(self.path.clone())
+    }
+
+    ///rdma
+    pub fn rdma(&self) -> Rdma {
+        Rdma::new(self.path.clone())
+    }
+
+    ///misc
+    pub fn misc(&self) -> Misc {
+        Misc::new(self.path.clone())
     }
 }
 
+/// The cgroup.subtree_control line enabling `enables` and disabling `disables`, e.g.
+/// `+memory +pids -io`.
+pub(crate) fn subtree_control_line(enables: Vec<ControllerType>, disables: Option<Vec<ControllerType>>) -> String {
+    let mut line: String = enables
+        .iter()
+        .map(|e| {
+            let mut s = e.to_string();
+            s.insert(0, '+');
+            s
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+    if let Some(disables) = disables {
+        let _line = disables
+            .iter()
+            .map(|e| {
+                let mut s = e.to_string();
+                s.insert(0, '-');
+                s
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        line.push(' ');
+        line.push_str(&_line);
+    };
+    line
+}
+
+fn write_pid(procs: &Path, pid: i32) -> io::Result<()> {
+    let mut file = OpenOptions::new().append(true).open(procs)?;
+    file.write_all(pid.to_string().as_bytes())
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum CGroupType {
     Domain,
@@ -242,17 +502,17 @@ impl FromStr for CGroupType {
     type Err = CGroupError;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        return match s {
+        match s {
             "domain\n" => Ok(CGroupType::Domain),
             "domain threaded\n" => Ok(CGroupType::DomainThreaded),
             "domain invalid\n" => Ok(CGroupType::DomainInvalid),
             "threaded\n" => Ok(CGroupType::Threaded),
             _ => Err(CGroupError::UnknownFieldErr(String::from(s)))
-        };
+        }
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct CGroupEvent {
     pub populated: bool,
     pub frozen: bool,
@@ -285,10 +545,7 @@ impl FromStr for CGroupEvent {
                 .ok_or(CGroupError::UnknownFieldErr(String::from(s)))?;
             let val = kv.next()
                 .ok_or(CGroupError::UnknownFieldErr(String::from(s)))?;
-            let val = match val {
-                "1" => true,
-                _ => false
-            };
+            let val = val == "1";
             event.setter(key, val);
         }
         Ok(event)
@@ -306,7 +563,7 @@ impl FromStr for Max {
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         let mut splits = s.split('\n');
-        return match splits.next() {
+        match splits.next() {
             Some("max") => Ok(Max::Max),
             Some(max) => {
                 let val = u32::from_str(max)
@@ -314,7 +571,7 @@ impl FromStr for Max {
                 Ok(Max::Val(val))
             }
             None => Err(CGroupError::EmptyFileErr)
-        };
+        }
     }
 }
 
@@ -378,4 +635,44 @@ impl FromStr for Freeze {
         }
         Err(CGroupError::UnknownFieldErr(s.to_string()))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::ErrorKind, path::Path, thread, time::Duration};
+
+    use super::CGroup;
+    use crate::error::CGroupError;
+
+    #[test]
+    fn owned_handle() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CGroup>();
+
+        let c_group = CGroup::new(Path::new("/sys/fs/cgroup/job").to_path_buf());
+        let memory = c_group.memory();
+        let handle = thread::spawn(move || c_group.path().to_path_buf());
+        assert_eq!(handle.join().unwrap(), Path::new("/sys/fs/cgroup/job"));
+        drop(memory);
+    }
+
+    #[test]
+    fn wait_on_missing_cgroup() {
+        let dir = tempfile::tempdir().unwrap();
+        let c_group = CGroup::new(dir.path().join("missing"));
+        assert_eq!(c_group.wait_until_empty(Duration::from_millis(10)), Ok(()));
+        assert_eq!(c_group.wait_frozen(Duration::from_millis(10)), Err(CGroupError::FSErr(ErrorKind::NotFound)));
+    }
+
+    #[test]
+    fn migrate_skips_unmapped_pids() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        for path in [&from, &to] {
+            fs::create_dir(path).unwrap();
+        }
+        fs::write(from.join("cgroup.procs"), "0\n0\n").unwrap();
+        fs::write(to.join("cgroup.procs"), "").unwrap();
+        assert_eq!(CGroup::new(from).migrate_all_to(&CGroup::new(to.clone())), Ok(0));
+        assert_eq!(fs::read_to_string(to.join("cgroup.procs")).unwrap(), "");
+    }
+}
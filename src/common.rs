@@ -1,31 +1,32 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 use crate::error::{
     CGroupError,
     Result
 };
 
-#[derive(Debug, Eq, PartialEq)]
-pub enum Max {
+///A limit that is either a value or `max`. Most limits fit in a u32; byte counts use `Max<u64>`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Max<T = u32> {
     Max,
-    Val(u32),
+    Val(T),
 }
 
-impl ToString for Max {
-    fn to_string(&self) -> String {
+impl<T: fmt::Display> fmt::Display for Max<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Max::Max => String::from("max"),
-            Max::Val(max) => max.to_string()
+            Max::Max => f.write_str("max"),
+            Max::Val(max) => write!(f, "{}", max)
         }
     }
 }
 
-impl FromStr for Max {
+impl<T: FromStr> FromStr for Max<T> {
     type Err = CGroupError;
 
     fn from_str(s: &str) -> Result<Self> {
         let max = match s {
             "max" => Max::Max,
-            _ => Max::Val(u32::from_str(s)
+            _ => Max::Val(T::from_str(s)
                 .map_err(|_| CGroupError::UnknownFieldErr(s.to_string()))?)
         };
         Ok(max)
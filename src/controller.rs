@@ -1,41 +1,76 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 use crate::error::CGroupError;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum ControllerType {
-    CPUSET, CPU, IO, MEMORY, PIDS
+    CPUSET, CPU, IO, MEMORY, PIDS, HUGETLB, RDMA, MISC,
+    ///A controller this crate has no API for, such as `perf_event`. It can still be enabled and
+    ///disabled through cgroup.subtree_control.
+    Other(String),
 }
 
 impl ControllerType {
+    ///The common controllers. Writing cgroup.subtree_control fails as a whole if any of them is
+    ///unavailable, so optional controllers such as hugetlb are left out.
     pub fn all() -> Vec<ControllerType> {
         vec![Self::CPUSET, Self::CPU, Self::IO, Self::MEMORY, Self::PIDS]
     }
+
+    ///All controllers this crate has an API for, including optional ones
+    pub fn all_known() -> Vec<ControllerType> {
+        vec![Self::CPUSET, Self::CPU, Self::IO, Self::MEMORY, Self::PIDS, Self::HUGETLB, Self::RDMA, Self::MISC]
+    }
 }
 
 impl FromStr for ControllerType {
     type Err = CGroupError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        return match s {
+        match s {
             "cpuset" => Ok(ControllerType::CPUSET),
             "cpu" => Ok(ControllerType::CPU),
             "io" => Ok(ControllerType::IO),
             "memory" => Ok(ControllerType::MEMORY),
             "pids" => Ok(ControllerType::PIDS),
-            _ => Err(CGroupError::UnknownFieldErr(String::from(s)))
+            "hugetlb" => Ok(ControllerType::HUGETLB),
+            "rdma" => Ok(ControllerType::RDMA),
+            "misc" => Ok(ControllerType::MISC),
+            _ if s.is_empty() || s.contains(char::is_whitespace) => Err(CGroupError::UnknownFieldErr(String::from(s))),
+            _ => Ok(ControllerType::Other(String::from(s))),
         }
     }
 }
 
-impl ToString for ControllerType {
-    fn to_string(&self) -> String {
+impl fmt::Display for ControllerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
             ControllerType::CPUSET => "cpuset",
             ControllerType::CPU => "cpu",
             ControllerType::IO => "io",
             ControllerType::MEMORY => "memory",
             ControllerType::PIDS => "pids",
+            ControllerType::HUGETLB => "hugetlb",
+            ControllerType::RDMA => "rdma",
+            ControllerType::MISC => "misc",
+            ControllerType::Other(s) => s,
         };
-        s.to_string()
+        f.write_str(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::ControllerType;
+    use crate::util::read_space_separated_values;
+
+    #[test]
+    fn unknown_controllers_are_kept() {
+        let controllers: Vec<ControllerType> = read_space_separated_values("cpu hugetlb perf_event\n".to_string());
+        let perf_event = ControllerType::Other("perf_event".to_string());
+        assert_eq!(controllers, vec![ControllerType::CPU, ControllerType::HUGETLB, perf_event.clone()]);
+        assert_eq!(perf_event.to_string(), "perf_event");
+        assert!(ControllerType::from_str("").is_err());
     }
-}
\ No newline at end of file
+}
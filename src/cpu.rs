@@ -1,6 +1,9 @@
 use std::{
+    fmt,
     path::Path,
     str::FromStr,
+    sync::Arc,
+    time::Duration,
 };
 
 use crate::{
@@ -8,53 +11,64 @@ use crate::{
         CGroupError, Result,
     },
     FlatKeyedSetter,
-    psi::CPUPressure,
+    psi::{
+        CPUPressure,
+        PressureTrigger,
+        Stall,
+    },
     util::{
-        read_flat_keyed_file, read_single_value, write_single_value,
+        CGroupDir, read_flat_keyed_file, read_single_value, read_value, write_single_value,
     },
 };
 use crate::common::Max;
 
-#[derive(Debug, Eq, PartialEq)]
-pub struct Cpu<'a> {
-    path: &'a Path
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cpu<F = Arc<Path>> {
+    dir: F
+}
+
+impl Cpu {
+    pub fn new<P: Into<Arc<Path>>>(path: P) -> Self {
+        Cpu::with_dir(path.into())
+    }
 }
 
-impl<'a> Cpu<'a> {
-    pub fn new(path: &'a Path) -> Self {
+impl<F: CGroupDir> Cpu<F> {
+    ///Open the interface files through `dir`, e.g. a [`CGroupFd`](crate::fd::CGroupFd).
+    pub fn with_dir(dir: F) -> Self {
         Cpu {
-            path
+            dir
         }
     }
 
     pub fn stat(&self) -> Result<Stat> {
         let filename = "cpu.stat";
-        read_flat_keyed_file(&self.path, filename)
+        read_flat_keyed_file(&self.dir, filename)
     }
 
     pub fn weight(&self) -> Result<u16> {
         let filename = "cpu.weight";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
     }
 
     pub fn set_weight(&self, w: u16) -> Result<()> {
         let filename = "cpu.weight";
-        write_single_value(&self.path, filename, w)
+        write_single_value(&self.dir, filename, w)
     }
 
     pub fn weight_nice(&self) -> Result<i8> {
         let filename = "cpu.weight.nice";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
     }
 
     pub fn set_weight_nice(&self, n: i8) -> Result<()> {
         let filename = "cpu.weight.nice";
-        write_single_value(&self.path, filename, n)
+        write_single_value(&self.dir, filename, n)
     }
 
     pub fn max(&self) -> Result<CPUMax> {
         let filename = "cpu.max";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
     }
 
     pub fn set_max(&self, max: u32, period: Option<u32>) -> Result<()> {
@@ -63,23 +77,28 @@ impl<'a> Cpu<'a> {
             max: Max::Val(max),
             period,
         };
-        write_single_value(&self.path, filename, max)
+        write_single_value(&self.dir, filename, max)
     }
 
     pub fn pressure(&self) -> Result<CPUPressure> {
         let filename = "cpu.pressure";
-        read_single_value(&self.path, filename)
+        read_value(&self.dir, filename)
+    }
+
+    ///Register a PSI trigger on cpu.pressure
+    pub fn pressure_trigger(&self, stall: Stall, threshold: Duration, window: Duration) -> Result<PressureTrigger> {
+        PressureTrigger::open(&self.dir, "cpu.pressure", stall, threshold, window)
     }
 
     // uclamp
     // pub fn uclamp_min(&self) -> Result<f32> {
     //     let filename = "cpu.uclamp.min";
-    //     read_single_value(&self.path, filename)
+    //     read_single_value(&self.dir, filename)
     // }
     //
     // pub fn set_uclamp_min(&self, min: f32) -> Result<()> {
     //     let filename = "cpu.uclamp.min";
-    //     write_single_value(&self.path, filename, min)
+    //     write_single_value(&self.dir, filename, min)
     // }
 }
 
@@ -147,13 +166,12 @@ impl FromStr for CPUMax {
     }
 }
 
-impl ToString for CPUMax {
-    fn to_string(&self) -> String {
-        let max = self.max.to_string();
-        return if let Some(period) = self.period {
-            format!("{} {}", max, period.to_string())
+impl fmt::Display for CPUMax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(period) = self.period {
+            write!(f, "{} {}", self.max, period)
         } else {
-            max
-        };
+            write!(f, "{}", self.max)
+        }
     }
 }
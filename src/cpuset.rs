@@ -0,0 +1,476 @@
+use std::{
+    collections::{
+        btree_set,
+        BTreeSet,
+    },
+    fmt,
+    iter::FromIterator,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{
+    error::{
+        CGroupError,
+        Result,
+    },
+    util::{
+        CGroupDir,
+        read_value,
+        write_single_value,
+    },
+};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cpuset<F = Arc<Path>> {
+    dir: F
+}
+
+impl Cpuset {
+    pub fn new<P: Into<Arc<Path>>>(path: P) -> Self {
+        Cpuset::with_dir(path.into())
+    }
+}
+
+impl<F: CGroupDir> Cpuset<F> {
+    ///Open the interface files through `dir`, e.g. a [`CGroupFd`](crate::fd::CGroupFd).
+    pub fn with_dir(dir: F) -> Self {
+        Cpuset {
+            dir
+        }
+    }
+
+    ///The requested CPUs. Empty means the CPUs of the parent are used.
+    pub fn cpus(&self) -> Result<CpuList> {
+        let filename = "cpuset.cpus";
+        read_value(&self.dir, filename)
+    }
+
+    pub fn set_cpus(&self, cpus: &CpuList) -> Result<()> {
+        let filename = "cpuset.cpus";
+        write_list(&self.dir, filename, cpus)
+    }
+
+    ///The CPUs actually granted, after applying the restrictions of the ancestors.
+    pub fn cpus_effective(&self) -> Result<CpuList> {
+        let filename = "cpuset.cpus.effective";
+        read_value(&self.dir, filename)
+    }
+
+    ///The requested memory nodes. Empty means the nodes of the parent are used.
+    pub fn mems(&self) -> Result<CpuList> {
+        let filename = "cpuset.mems";
+        read_value(&self.dir, filename)
+    }
+
+    pub fn set_mems(&self, mems: &CpuList) -> Result<()> {
+        let filename = "cpuset.mems";
+        write_list(&self.dir, filename, mems)
+    }
+
+    pub fn mems_effective(&self) -> Result<CpuList> {
+        let filename = "cpuset.mems.effective";
+        read_value(&self.dir, filename)
+    }
+
+    ///CPUs reserved for creating a partition. Available since kernel 6.7.
+    pub fn cpus_exclusive(&self) -> Result<CpuList> {
+        let filename = "cpuset.cpus.exclusive";
+        read_value(&self.dir, filename)
+    }
+
+    pub fn set_cpus_exclusive(&self, cpus: &CpuList) -> Result<()> {
+        let filename = "cpuset.cpus.exclusive";
+        write_list(&self.dir, filename, cpus)
+    }
+
+    pub fn cpus_exclusive_effective(&self) -> Result<CpuList> {
+        let filename = "cpuset.cpus.exclusive.effective";
+        read_value(&self.dir, filename)
+    }
+
+    ///cpuset.cpus.partition, only present on non-root cgroups
+    pub fn partition(&self) -> Result<PartitionState> {
+        let filename = "cpuset.cpus.partition";
+        read_value(&self.dir, filename)
+    }
+
+    pub fn set_partition(&self, partition: Partition) -> Result<()> {
+        let filename = "cpuset.cpus.partition";
+        write_single_value(&self.dir, filename, partition)
+    }
+
+    ///Turn this cgroup into a partition root with its own scheduling domain.
+    ///
+    ///The kernel accepts the request even when the partition cannot be set up and only marks it
+    ///invalid. This reads the state back and reports such a partition as
+    ///`InvalidPartitionErr`. The cgroup stays an invalid partition root in that case, and the
+    ///kernel turns it into a valid one as soon as the cause is gone.
+    pub fn make_root(&self) -> Result<()> {
+        self.make_partition(Partition::Root)
+    }
+
+    ///Turn this cgroup into an isolated partition, whose CPUs are not load balanced. See
+    ///[`Cpuset::make_root`] for how invalid partitions are reported.
+    pub fn make_isolated(&self) -> Result<()> {
+        self.make_partition(Partition::Isolated)
+    }
+
+    ///Turn a partition root back into an ordinary member of its parent's partition.
+    pub fn make_member(&self) -> Result<()> {
+        self.make_partition(Partition::Member)
+    }
+
+    fn make_partition(&self, partition: Partition) -> Result<()> {
+        self.set_partition(partition)?;
+        self.partition()?.check()
+    }
+}
+
+///Write a list with a trailing newline, so that an empty list still reaches the kernel.
+fn write_list(parent: &impl CGroupDir, filename: &str, list: &CpuList) -> Result<()> {
+    write_single_value(parent, filename, format!("{}\n", list))
+}
+
+/// Upper bound on the numbers [`CpuList::from_str`] accepts. The kernel supports at most 8192
+/// CPUs (NR_CPUS) and 1024 memory nodes (MAX_NUMNODES).
+pub const MAX_CPUS: u32 = 8192;
+
+/// A set of CPU or memory node numbers in the kernel's list format, e.g. `0-3,8,10-11`.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct CpuList(BTreeSet<u32>);
+
+impl CpuList {
+    pub fn new() -> Self {
+        CpuList(BTreeSet::new())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, cpu: u32) -> bool {
+        self.0.contains(&cpu)
+    }
+
+    pub fn insert(&mut self, cpu: u32) -> bool {
+        self.0.insert(cpu)
+    }
+
+    pub fn remove(&mut self, cpu: u32) -> bool {
+        self.0.remove(&cpu)
+    }
+
+    pub fn iter(&self) -> btree_set::Iter<'_, u32> {
+        self.0.iter()
+    }
+
+    pub fn union(&self, other: &CpuList) -> CpuList {
+        self.0.union(&other.0).copied().collect()
+    }
+
+    pub fn intersection(&self, other: &CpuList) -> CpuList {
+        self.0.intersection(&other.0).copied().collect()
+    }
+
+    pub fn difference(&self, other: &CpuList) -> CpuList {
+        self.0.difference(&other.0).copied().collect()
+    }
+
+    pub fn is_subset(&self, other: &CpuList) -> bool {
+        self.0.is_subset(&other.0)
+    }
+
+    pub fn is_disjoint(&self, other: &CpuList) -> bool {
+        self.0.is_disjoint(&other.0)
+    }
+}
+
+impl FromIterator<u32> for CpuList {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        CpuList(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a CpuList {
+    type Item = &'a u32;
+    type IntoIter = btree_set::Iter<'a, u32>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl FromStr for CpuList {
+    type Err = CGroupError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let err = || CGroupError::UnknownFieldErr(s.to_string());
+        let mut list = CpuList::new();
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(list);
+        }
+        for range in s.split(',') {
+            match range.split_once('-') {
+                Some((first, last)) => {
+                    let first = u32::from_str(first).map_err(|_| err())?;
+                    let last = u32::from_str(last).map_err(|_| err())?;
+                    // bounded, so that a huge range cannot exhaust memory
+                    if first > last || last >= MAX_CPUS {
+                        return Err(err());
+                    }
+                    list.0.extend(first..=last);
+                }
+                None => {
+                    let cpu = u32::from_str(range).map_err(|_| err())?;
+                    if cpu >= MAX_CPUS {
+                        return Err(err());
+                    }
+                    list.0.insert(cpu);
+                }
+            }
+        }
+        Ok(list)
+    }
+}
+
+impl fmt::Display for CpuList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut iter = self.0.iter().copied().peekable();
+        let mut first = true;
+        while let Some(start) = iter.next() {
+            let mut end = start;
+            while end.checked_add(1).is_some_and(|next| iter.peek() == Some(&next)) {
+                end = iter.next().unwrap_or(end);
+            }
+            if !first {
+                f.write_str(",")?;
+            }
+            first = false;
+            if start == end {
+                write!(f, "{}", start)?;
+            } else {
+                write!(f, "{}-{}", start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+///cpuset.cpus.partition
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Partition {
+    ///An ordinary member of the parent's partition
+    Member,
+    ///A partition root with its own scheduling domain
+    Root,
+    ///A partition root whose CPUs are taken out of load balancing
+    Isolated,
+}
+
+impl FromStr for Partition {
+    type Err = CGroupError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "member" => Ok(Partition::Member),
+            "root" => Ok(Partition::Root),
+            "isolated" => Ok(Partition::Isolated),
+            _ => Err(CGroupError::UnknownFieldErr(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Partition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Partition::Member => f.write_str("member"),
+            Partition::Root => f.write_str("root"),
+            Partition::Isolated => f.write_str("isolated"),
+        }
+    }
+}
+
+///The content of cpuset.cpus.partition. The kernel keeps the requested partition type, but
+///reports it as invalid while the partition cannot be set up.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PartitionState {
+    pub partition: Partition,
+    ///Why the partition is invalid, if it is
+    pub invalid: Option<InvalidPartition>,
+}
+
+impl PartitionState {
+    pub fn is_valid(&self) -> bool {
+        self.invalid.is_none()
+    }
+
+    ///Turn an invalid partition into an `InvalidPartitionErr`.
+    pub fn check(self) -> Result<()> {
+        match self.invalid {
+            None => Ok(()),
+            Some(reason) => Err(CGroupError::InvalidPartitionErr {
+                partition: self.partition,
+                reason,
+            }),
+        }
+    }
+}
+
+impl FromStr for PartitionState {
+    type Err = CGroupError;
+
+    //root invalid (Cpu list in cpuset.cpus not exclusive)
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (partition, rest) = s.split_once(' ').unwrap_or((s, ""));
+        let partition = Partition::from_str(partition)?;
+        let rest = rest.trim();
+        let invalid = if rest.is_empty() {
+            None
+        } else {
+            let reason = rest.strip_prefix("invalid")
+                .ok_or(CGroupError::UnknownFieldErr(s.to_string()))?
+                .trim();
+            let reason = reason.strip_prefix('(')
+                .and_then(|r| r.strip_suffix(')'))
+                .unwrap_or(reason);
+            Some(InvalidPartition::from(reason))
+        };
+        Ok(PartitionState {
+            partition,
+            invalid,
+        })
+    }
+}
+
+///Why the kernel considers a partition invalid, as reported in cpuset.cpus.partition.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum InvalidPartition {
+    ///Invalid cpu list in cpuset.cpus.exclusive
+    InvalidCpus,
+    ///Parent is an invalid partition root
+    InvalidParent,
+    ///Parent is not a partition root
+    ParentNotPartition,
+    ///Cpu list in cpuset.cpus not exclusive
+    NotExclusive,
+    ///Parent unable to distribute cpu downstream
+    NoCpus,
+    ///No cpu available due to hotplug
+    Hotplug,
+    ///cpuset.cpus and cpuset.cpus.effective are empty
+    CpusEmpty,
+    ///partition config conflicts with housekeeping setup
+    Housekeeping,
+    ///Enable partition not permitted
+    Access,
+    ///Have remote partition underneath
+    RemotePartition,
+    ///A reason this crate does not know. Kernels older than 6.1 give no reason at all, which
+    ///shows up as an empty string.
+    Other(String),
+}
+
+impl From<&str> for InvalidPartition {
+    fn from(reason: &str) -> Self {
+        match reason {
+            "Invalid cpu list in cpuset.cpus.exclusive" => InvalidPartition::InvalidCpus,
+            "Parent is an invalid partition root" => InvalidPartition::InvalidParent,
+            "Parent is not a partition root" => InvalidPartition::ParentNotPartition,
+            "Cpu list in cpuset.cpus not exclusive" => InvalidPartition::NotExclusive,
+            "Parent unable to distribute cpu downstream" => InvalidPartition::NoCpus,
+            "No cpu available due to hotplug" => InvalidPartition::Hotplug,
+            "cpuset.cpus and cpuset.cpus.effective are empty" => InvalidPartition::CpusEmpty,
+            "partition config conflicts with housekeeping setup" => InvalidPartition::Housekeeping,
+            "Enable partition not permitted" => InvalidPartition::Access,
+            "Have remote partition underneath" => InvalidPartition::RemotePartition,
+            _ => InvalidPartition::Other(reason.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for InvalidPartition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            InvalidPartition::InvalidCpus => "Invalid cpu list in cpuset.cpus.exclusive",
+            InvalidPartition::InvalidParent => "Parent is an invalid partition root",
+            InvalidPartition::ParentNotPartition => "Parent is not a partition root",
+            InvalidPartition::NotExclusive => "Cpu list in cpuset.cpus not exclusive",
+            InvalidPartition::NoCpus => "Parent unable to distribute cpu downstream",
+            InvalidPartition::Hotplug => "No cpu available due to hotplug",
+            InvalidPartition::CpusEmpty => "cpuset.cpus and cpuset.cpus.effective are empty",
+            InvalidPartition::Housekeeping => "partition config conflicts with housekeeping setup",
+            InvalidPartition::Access => "Enable partition not permitted",
+            InvalidPartition::RemotePartition => "Have remote partition underneath",
+            InvalidPartition::Other(reason) if reason.is_empty() => "unknown reason",
+            InvalidPartition::Other(reason) => reason,
+        };
+        f.write_str(reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{CpuList, InvalidPartition, Partition, PartitionState};
+    use crate::error::CGroupError;
+
+    #[test]
+    fn cpu_list_round_trip() {
+        let list = CpuList::from_str("0-3,8,10-11\n").unwrap();
+        assert_eq!(list.len(), 7);
+        assert!(list.contains(10) && !list.contains(9));
+        assert_eq!(list.to_string(), "0-3,8,10-11");
+        assert_eq!(CpuList::from_str("5,1,2,3").unwrap().to_string(), "1-3,5");
+        assert_eq!(CpuList::from_str("\n").unwrap(), CpuList::new());
+        assert_eq!(CpuList::new().to_string(), "");
+        assert!(CpuList::from_str("3-1").is_err());
+        assert!(CpuList::from_str("a").is_err());
+        assert!(CpuList::from_str("1,,2").is_err());
+        assert_eq!(CpuList::from_str("0-8191").unwrap().len(), 8192);
+        assert_eq!(CpuList::from_str("0-4294967295"), Err(CGroupError::UnknownFieldErr("0-4294967295".to_string())));
+        assert_eq!(vec![u32::MAX - 1, u32::MAX].into_iter().collect::<CpuList>().to_string(), "4294967294-4294967295");
+        assert!(CpuList::from_str("8192").is_err());
+    }
+
+    #[test]
+    fn cpu_list_set_operations() {
+        let a = CpuList::from_str("0-7").unwrap();
+        let b = CpuList::from_str("4-11").unwrap();
+        assert_eq!(a.union(&b).to_string(), "0-11");
+        assert_eq!(a.intersection(&b).to_string(), "4-7");
+        assert_eq!(a.difference(&b).to_string(), "0-3");
+        assert!(a.intersection(&b).is_subset(&a));
+        assert!(a.difference(&b).is_disjoint(&b));
+        let collected: CpuList = vec![9, 2, 3].into_iter().collect();
+        assert_eq!(collected.to_string(), "2-3,9");
+    }
+
+    #[test]
+    fn parse_partition() {
+        let state = PartitionState::from_str("member\n").unwrap();
+        assert_eq!(state, PartitionState { partition: Partition::Member, invalid: None });
+        let state = PartitionState::from_str("isolated invalid (Cpu list in cpuset.cpus not exclusive)\n").unwrap();
+        assert_eq!(state.partition, Partition::Isolated);
+        assert_eq!(state.invalid, Some(InvalidPartition::NotExclusive));
+        assert_eq!(state.clone().check(), Err(CGroupError::InvalidPartitionErr {
+            partition: Partition::Isolated,
+            reason: InvalidPartition::NotExclusive,
+        }));
+        let state = PartitionState::from_str("root invalid (Parent is not a partition root)\n").unwrap();
+        assert_eq!(state.invalid, Some(InvalidPartition::ParentNotPartition));
+        let state = PartitionState::from_str("root invalid\n").unwrap();
+        assert_eq!(state.invalid, Some(InvalidPartition::Other(String::new())));
+        assert!(!state.is_valid());
+        assert_eq!(PartitionState::from_str("root\n").unwrap().check(), Ok(()));
+        assert!(PartitionState::from_str("leaf").is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    os::unix::fs::{
+        FileTypeExt,
+        MetadataExt,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+};
+
+use crate::{
+    error::{
+        CGroupError,
+        Result,
+    },
+    io::DeviceNumber,
+};
+
+/// Block devices by number, e.g. `/sys/dev/block/259:0`.
+pub const SYS_DEV_BLOCK: &str = "/sys/dev/block";
+/// Block devices by name, e.g. `/sys/class/block/nvme0n1`.
+pub const SYS_CLASS_BLOCK: &str = "/sys/class/block";
+
+/// A block device in any of the forms an operator may know it by. IO settings only apply to
+/// whole disks, so partitions resolve to the disk they are on.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BlockDevice {
+    ///`MAJ:MIN`, used as is
+    Number(DeviceNumber),
+    ///A device node such as `/dev/nvme0n1`, or any other path, which stands for the device the
+    ///filesystem holding it lives on
+    Path(PathBuf),
+    ///A kernel device name such as `nvme0n1` or `sda1`
+    Name(String),
+}
+
+impl BlockDevice {
+    pub fn resolve(&self) -> Result<DeviceNumber> {
+        match self {
+            BlockDevice::Number(number) => Ok(*number),
+            BlockDevice::Path(path) => {
+                let meta = fs::metadata(path).map_err(|e| CGroupError::FSErr(e.kind()))?;
+                let dev = if meta.file_type().is_block_device() {
+                    meta.rdev()
+                } else {
+                    meta.dev()
+                };
+                let number = DeviceNumber {
+                    maj: libc::major(dev),
+                    min: libc::minor(dev),
+                };
+                whole_disk(number)
+            }
+            BlockDevice::Name(name) => {
+                let dev = Path::new(SYS_CLASS_BLOCK).join(name).join("dev");
+                whole_disk(read_device_number(&dev)?)
+            }
+        }
+    }
+}
+
+/// Map a partition to the disk it is on. Other devices are returned unchanged. Devices without
+/// an entry in /sys/dev/block, such as the anonymous devices of tmpfs or overlayfs, fail with
+/// `NotFound`.
+pub fn whole_disk(number: DeviceNumber) -> Result<DeviceNumber> {
+    let sys = Path::new(SYS_DEV_BLOCK).join(number.to_string());
+    let sys = fs::canonicalize(sys).map_err(|e| CGroupError::FSErr(e.kind()))?;
+    if !sys.join("partition").exists() {
+        return Ok(number);
+    }
+    let disk = sys.parent().ok_or(CGroupError::FSErr(ErrorKind::NotFound))?;
+    read_device_number(&disk.join("dev"))
+}
+
+/// The kernel name of a block device, e.g. `nvme0n1`.
+pub fn device_name(number: DeviceNumber) -> Result<String> {
+    let sys = Path::new(SYS_DEV_BLOCK).join(number.to_string());
+    let target = fs::read_link(sys).map_err(|e| CGroupError::FSErr(e.kind()))?;
+    target.file_name()
+        .and_then(|name| name.to_str())
+        .map(String::from)
+        .ok_or(CGroupError::UnknownFieldErr(target.display().to_string()))
+}
+
+fn read_device_number(path: &Path) -> Result<DeviceNumber> {
+    let content = fs::read_to_string(path).map_err(|e| CGroupError::FSErr(e.kind()))?;
+    DeviceNumber::from_str(content.trim())
+}
+
+impl From<DeviceNumber> for BlockDevice {
+    fn from(number: DeviceNumber) -> Self {
+        BlockDevice::Number(number)
+    }
+}
+
+impl From<PathBuf> for BlockDevice {
+    fn from(path: PathBuf) -> Self {
+        BlockDevice::Path(path)
+    }
+}
+
+impl From<&Path> for BlockDevice {
+    fn from(path: &Path) -> Self {
+        BlockDevice::Path(PathBuf::from(path))
+    }
+}
+
+impl From<&str> for BlockDevice {
+    ///`MAJ:MIN` is a device number, anything containing a `/` a path, and the rest a name.
+    fn from(s: &str) -> Self {
+        if let Ok(number) = DeviceNumber::from_str(s) {
+            BlockDevice::Number(number)
+        } else if s.contains('/') {
+            BlockDevice::Path(PathBuf::from(s))
+        } else {
+            BlockDevice::Name(s.to_string())
+        }
+    }
+}
+
+impl From<String> for BlockDevice {
+    fn from(s: String) -> Self {
+        BlockDevice::from(s.as_str())
+    }
+}
+
+impl From<&String> for BlockDevice {
+    fn from(s: &String) -> Self {
+        BlockDevice::from(s.as_str())
+    }
+}
+
+impl FromStr for BlockDevice {
+    type Err = CGroupError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            return Err(CGroupError::UnknownFieldErr(s.to_string()));
+        }
+        Ok(BlockDevice::from(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::ErrorKind, path::PathBuf};
+
+    use super::{device_name, whole_disk, BlockDevice};
+    use crate::{error::CGroupError, io::DeviceNumber};
+
+    #[test]
+    fn classify_block_device() {
+        let number = DeviceNumber { maj: 259, min: 0 };
+        assert_eq!(BlockDevice::from("259:0"), BlockDevice::Number(number));
+        assert_eq!(BlockDevice::from("/dev/nvme0n1"), BlockDevice::Path(PathBuf::from("/dev/nvme0n1")));
+        assert_eq!(BlockDevice::from("var/lib"), BlockDevice::Path(PathBuf::from("var/lib")));
+        assert_eq!(BlockDevice::from("nvme0n1"), BlockDevice::Name("nvme0n1".to_string()));
+        assert_eq!(BlockDevice::from(number).resolve(), Ok(number));
+        assert_eq!(BlockDevice::from(String::from("sda1")), BlockDevice::Name("sda1".to_string()));
+        assert_eq!(BlockDevice::from(&String::from("259:0")), BlockDevice::Number(number));
+    }
+
+    #[test]
+    fn resolve_path() {
+        // the root filesystem is on a disk, or on an anonymous device such as overlayfs
+        match BlockDevice::from("/").resolve() {
+            Ok(number) => {
+                assert_eq!(whole_disk(number), Ok(number));
+                assert!(device_name(number).is_ok());
+            }
+            Err(err) => assert_eq!(err, CGroupError::FSErr(ErrorKind::NotFound)),
+        }
+    }
+}
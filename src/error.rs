@@ -9,6 +9,21 @@ pub enum CGroupError {
     EmptyFileErr,
     #[error("write 0 byte")]
     WriteZeroByteErr,
+    #[error("`{0}` is not a cgroup2 directory")]
+    NotCGroup2Err(String),
+    #[error("environment variable `{0}` is not set")]
+    EnvVarErr(String),
+    #[error("no cgroup2 filesystem is mounted")]
+    MountNotFoundErr,
+    #[error("cgroup `{0}` is not visible from the cgroup2 mount")]
+    NotVisibleErr(String),
+    #[error("timed out")]
+    TimeoutErr,
+    #[error("{partition} partition is invalid: {reason}")]
+    InvalidPartitionErr {
+        partition: crate::cpuset::Partition,
+        reason: crate::cpuset::InvalidPartition,
+    },
 
     #[error("the data for key `{0}` is not available")]
     Redaction(String),
@@ -0,0 +1,320 @@
+use std::{
+    fs::File,
+    hash::Hash,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::MetadataExt,
+        io::{
+            AsRawFd,
+            FromRawFd,
+            OwnedFd,
+            RawFd,
+        },
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+};
+
+use crate::{
+    cgroup::{
+        subtree_control_line,
+        CGroupEvent,
+        CGroupStat,
+        CGroupType,
+        Freeze,
+    },
+    controller::ControllerType,
+    cpu::Cpu,
+    cpuset::Cpuset,
+    error::{
+        CGroupError,
+        Result,
+    },
+    hugetlb::HugeTlb,
+    io::IO,
+    memory::Memory,
+    misc::Misc,
+    pids::Pids,
+    rdma::Rdma,
+    util::{
+        self,
+        c_string,
+        last_os_error,
+        read_newline_separated_values,
+        read_space_separated_values,
+        CGroupDir,
+    },
+    FlatKeyedSetter,
+};
+use std::collections::HashMap;
+
+/// A cgroup handle backed by a directory file descriptor.
+///
+/// Interface files are opened relative to the descriptor with `openat`, so the handle keeps
+/// operating on the cgroup it was opened for even if that cgroup is removed and another one is
+/// created under the same name. Once the original cgroup is gone, every access fails with
+/// `NotFound`. This also holds for the controller views borrowed from the handle, e.g.
+/// [`CGroupFd::memory`].
+#[derive(Debug)]
+pub struct CGroupFd {
+    fd: OwnedFd,
+    path: PathBuf,
+}
+
+impl CGroupFd {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<CGroupFd> {
+        let path = path.as_ref();
+        let c_path = c_string(path.as_os_str().as_bytes())?;
+        let flags = libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC;
+        let fd = unsafe { libc::open(c_path.as_ptr(), flags) };
+        if fd < 0 {
+            return Err(last_os_error());
+        }
+        Ok(CGroupFd {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            path: PathBuf::from(path),
+        })
+    }
+
+    /// The path the handle was opened from. It may refer to a different cgroup by now.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The cgroup ID, which is the inode number of the cgroup directory. IDs are not reused
+    /// while the system is up, so comparing them detects a cgroup that has been replaced.
+    pub fn id(&self) -> Result<u64> {
+        let fd = self.fd.try_clone()
+            .map_err(|e| CGroupError::FSErr(e.kind()))?;
+        let meta = File::from(fd).metadata()
+            .map_err(|e| CGroupError::FSErr(e.kind()))?;
+        Ok(meta.ino())
+    }
+
+    /// Whether the handle's path still names the cgroup the handle was opened for.
+    pub fn is_current(&self) -> Result<bool> {
+        match std::fs::metadata(&self.path) {
+            Ok(meta) => Ok(meta.ino() == self.id()?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(CGroupError::FSErr(e.kind())),
+        }
+    }
+
+    /// Read a whole interface file.
+    pub fn read_file(&self, filename: &str) -> Result<String> {
+        CGroupDir::read_file(self, filename)
+    }
+
+    pub fn read_value<T: FromStr>(&self, filename: &str) -> Result<T> {
+        util::read_value(self, filename)
+    }
+
+    pub fn read_single_value<T: FromStr>(&self, filename: &str) -> Result<T> {
+        util::read_single_value(self, filename)
+    }
+
+    pub fn read_flat_keyed_file<V, T>(&self, filename: &str) -> Result<T>
+    where
+        V: FromStr,
+        T: FlatKeyedSetter<V>,
+    {
+        util::read_flat_keyed_file(self, filename)
+    }
+
+    pub fn read_flat_keyed_file_map<V: FromStr>(&self, filename: &str) -> Result<HashMap<String, V>> {
+        util::read_flat_keyed_file_map(self, filename)
+    }
+
+    pub fn read_nested_keyed_file<K, V>(&self, filename: &str) -> Result<HashMap<K, V>>
+    where
+        K: FromStr + Eq + Hash,
+        V: FromStr<Err=CGroupError>,
+    {
+        util::read_nested_keyed_file(self, filename)
+    }
+
+    /// Write `t` to an interface file with a single write call.
+    pub fn write_single_value<T: ToString>(&self, filename: &str, t: T) -> Result<()> {
+        util::write_single_value(self, filename, t)
+    }
+
+    ///cgroup.controllers
+    pub fn controllers(&self) -> Result<Vec<ControllerType>> {
+        Ok(read_space_separated_values(self.read_file("cgroup.controllers")?))
+    }
+
+    ///cgroup.subtree_control
+    pub fn subtree_control(&self) -> Result<Vec<ControllerType>> {
+        Ok(read_space_separated_values(self.read_file("cgroup.subtree_control")?))
+    }
+
+    ///cgroup.subtree_control
+    pub fn set_subtree_control(&self,
+                               enables: Vec<ControllerType>,
+                               disables: Option<Vec<ControllerType>>,
+    ) -> Result<()> {
+        self.write_single_value("cgroup.subtree_control", subtree_control_line(enables, disables))
+    }
+
+    ///cgroup.type
+    pub fn cg_type(&self) -> Result<CGroupType> {
+        self.read_value("cgroup.type")
+    }
+
+    ///cgroup.procs
+    pub fn procs(&self) -> Result<Vec<i32>> {
+        Ok(read_newline_separated_values(self.read_file("cgroup.procs")?))
+    }
+
+    ///cgroup.procs
+    pub fn add_pid(&self, pid: u32) -> Result<()> {
+        self.write_single_value("cgroup.procs", pid)
+    }
+
+    ///cgroup.threads
+    pub fn threads(&self) -> Result<Vec<i32>> {
+        Ok(read_newline_separated_values(self.read_file("cgroup.threads")?))
+    }
+
+    ///cgroup.threads
+    pub fn add_tid(&self, tid: u32) -> Result<()> {
+        self.write_single_value("cgroup.threads", tid)
+    }
+
+    ///cgroup.events
+    pub fn events(&self) -> Result<CGroupEvent> {
+        self.read_value("cgroup.events")
+    }
+
+    ///cgroup.stat
+    pub fn stat(&self) -> Result<CGroupStat> {
+        self.read_value("cgroup.stat")
+    }
+
+    ///cgroup.freeze
+    pub fn freeze_state(&self) -> Result<Freeze> {
+        self.read_value("cgroup.freeze")
+    }
+
+    ///cgroup.freeze
+    pub fn set_freeze_state(&self, freeze: bool) -> Result<()> {
+        self.write_single_value("cgroup.freeze", if freeze { "1" } else { "0" })
+    }
+
+    ///Request freezing the cgroup and its descendants. Freezing completes asynchronously.
+    pub fn freeze(&self) -> Result<()> {
+        self.set_freeze_state(true)
+    }
+
+    pub fn thaw(&self) -> Result<()> {
+        self.set_freeze_state(false)
+    }
+
+    ///Write cgroup.kill. Unlike [`CGroup::kill`](crate::cgroup::CGroup::kill) this returns
+    ///without waiting for the processes to exit, and fails with `NotFound` on kernels older
+    ///than 5.14 instead of signalling each process.
+    pub fn kill(&self) -> Result<()> {
+        self.write_single_value("cgroup.kill", 1)
+    }
+
+    pub fn cpu(&self) -> Cpu<&CGroupFd> {
+        Cpu::with_dir(self)
+    }
+
+    pub fn cpuset(&self) -> Cpuset<&CGroupFd> {
+        Cpuset::with_dir(self)
+    }
+
+    pub fn hugetlb(&self) -> HugeTlb<&CGroupFd> {
+        HugeTlb::with_dir(self)
+    }
+
+    pub fn memory(&self) -> Memory<&CGroupFd> {
+        Memory::with_dir(self)
+    }
+
+    pub fn io(&self) -> IO<&CGroupFd> {
+        IO::with_dir(self)
+    }
+
+    pub fn pids(&self) -> Pids<&CGroupFd> {
+        Pids::with_dir(self)
+    }
+
+    pub fn rdma(&self) -> Rdma<&CGroupFd> {
+        Rdma::with_dir(self)
+    }
+
+    pub fn misc(&self) -> Misc<&CGroupFd> {
+        Misc::with_dir(self)
+    }
+}
+
+impl CGroupDir for CGroupFd {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn open_file(&self, filename: &str, flags: libc::c_int) -> Result<File> {
+        let c_name = c_string(filename.as_bytes())?;
+        let fd = unsafe {
+            libc::openat(self.fd.as_raw_fd(), c_name.as_ptr(), flags | libc::O_CLOEXEC)
+        };
+        if fd < 0 {
+            return Err(last_os_error());
+        }
+        Ok(unsafe { File::from_raw_fd(fd) })
+    }
+}
+
+impl AsRawFd for CGroupFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::CGroupFd;
+    use crate::{cgroup::Freeze, common::Max, error::CGroupError};
+
+    #[test]
+    fn fd_survives_replacement() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("cgroup");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("cgroup.procs"), "1\n2\n").unwrap();
+        fs::write(dir.join("cgroup.freeze"), "0\n").unwrap();
+        fs::write(dir.join("pids.max"), "max\n").unwrap();
+
+        let handle = CGroupFd::open(&dir).unwrap();
+        let id = handle.id().unwrap();
+        assert_eq!(handle.procs(), Ok(vec![1, 2]));
+        assert_eq!(handle.is_current(), Ok(true));
+
+        // keep the old directory alive so its inode cannot be reused
+        let old = dir.with_extension("old");
+        fs::rename(&dir, &old).unwrap();
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("cgroup.procs"), "3\n").unwrap();
+        fs::write(dir.join("pids.max"), "5\n").unwrap();
+        assert_eq!(handle.is_current(), Ok(false));
+        assert_eq!(handle.procs(), Ok(vec![1, 2]));
+        assert_eq!(handle.pids().max(), Ok(Max::Max));
+        assert_eq!(handle.freeze(), Ok(()));
+        assert_eq!(handle.freeze_state(), Ok(Freeze(true)));
+        assert_eq!(fs::read_to_string(old.join("cgroup.freeze")).unwrap(), "1\n");
+        let replaced = CGroupFd::open(&dir).unwrap();
+        assert_eq!(replaced.procs(), Ok(vec![3]));
+        assert_eq!(replaced.pids().max(), Ok(Max::Val(5)));
+        assert_ne!(replaced.id().unwrap(), id);
+
+        fs::remove_dir_all(&old).unwrap();
+        assert_eq!(handle.procs(), Err(CGroupError::FSErr(std::io::ErrorKind::NotFound)));
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    fmt,
+    fs,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{
+    common::Max,
+    error::{
+        CGroupError,
+        Result,
+    },
+    util::{
+        CGroupDir,
+        read_flat_keyed_file,
+        read_single_value,
+        write_single_value,
+    },
+    FlatKeyedSetter,
+};
+
+/// Where the kernel lists the supported huge page sizes, one `hugepages-<size>kB` directory each.
+pub const HUGEPAGES_ROOT: &str = "/sys/kernel/mm/hugepages";
+
+const KB: u64 = 1 << 10;
+const MB: u64 = 1 << 20;
+const GB: u64 = 1 << 30;
+
+/// List the huge page sizes supported by the running kernel, smallest first.
+pub fn page_sizes() -> Result<Vec<PageSize>> {
+    let dir = fs::read_dir(HUGEPAGES_ROOT).map_err(|e| CGroupError::FSErr(e.kind()))?;
+    let mut sizes = Vec::new();
+    for entry in dir {
+        let entry = entry.map_err(|e| CGroupError::FSErr(e.kind()))?;
+        let name = entry.file_name();
+        if let Some(size) = name.to_str().and_then(|n| n.strip_prefix("hugepages-")) {
+            sizes.push(PageSize::from_str(size)?);
+        }
+    }
+    sizes.sort();
+    Ok(sizes)
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HugeTlb<F = Arc<Path>> {
+    dir: F
+}
+
+impl HugeTlb {
+    pub fn new<P: Into<Arc<Path>>>(path: P) -> Self {
+        HugeTlb::with_dir(path.into())
+    }
+}
+
+impl<F: CGroupDir> HugeTlb<F> {
+    ///Open the interface files through `dir`, e.g. a [`CGroupFd`](crate::fd::CGroupFd).
+    pub fn with_dir(dir: F) -> Self {
+        HugeTlb {
+            dir
+        }
+    }
+
+    ///The huge page sizes there are `hugetlb.<size>.*` files for.
+    pub fn page_sizes(&self) -> Result<Vec<PageSize>> {
+        page_sizes()
+    }
+
+    ///`hugetlb.<size>.max`, in bytes
+    pub fn max(&self, size: PageSize) -> Result<Max<u64>> {
+        read_single_value(&self.dir, &format!("hugetlb.{}.max", size))
+    }
+
+    pub fn set_max(&self, size: PageSize, max: Max<u64>) -> Result<()> {
+        write_single_value(&self.dir, &format!("hugetlb.{}.max", size), max)
+    }
+
+    ///`hugetlb.<size>.current`, in bytes
+    pub fn current(&self, size: PageSize) -> Result<u64> {
+        read_single_value(&self.dir, &format!("hugetlb.{}.current", size))
+    }
+
+    ///Allocation failures because of `hugetlb.<size>.max` in this cgroup and its descendants
+    pub fn events(&self, size: PageSize) -> Result<Event> {
+        read_flat_keyed_file(&self.dir, &format!("hugetlb.{}.events", size))
+    }
+
+    pub fn events_local(&self, size: PageSize) -> Result<Event> {
+        read_flat_keyed_file(&self.dir, &format!("hugetlb.{}.events.local", size))
+    }
+
+    ///`hugetlb.<size>.rsvd.max`, which also limits reservations made at mmap time
+    pub fn rsvd_max(&self, size: PageSize) -> Result<Max<u64>> {
+        read_single_value(&self.dir, &format!("hugetlb.{}.rsvd.max", size))
+    }
+
+    pub fn set_rsvd_max(&self, size: PageSize, max: Max<u64>) -> Result<()> {
+        write_single_value(&self.dir, &format!("hugetlb.{}.rsvd.max", size), max)
+    }
+
+    pub fn rsvd_current(&self, size: PageSize) -> Result<u64> {
+        read_single_value(&self.dir, &format!("hugetlb.{}.rsvd.current", size))
+    }
+}
+
+/// A huge page size. It is formatted the way the kernel names the hugetlb interface files,
+/// e.g. `2MB` or `1GB`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PageSize(u64);
+
+impl PageSize {
+    pub const fn from_bytes(bytes: u64) -> Self {
+        PageSize(bytes)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for PageSize {
+    type Err = CGroupError;
+
+    //2MB, 1GB, 64KB, or 2048kB as used in /sys/kernel/mm/hugepages
+    fn from_str(s: &str) -> Result<Self> {
+        let err = || CGroupError::UnknownFieldErr(s.to_string());
+        let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(err)?;
+        let (value, unit) = s.split_at(split);
+        let value = u64::from_str(value).map_err(|_| err())?;
+        let unit = match unit {
+            "kB" | "KB" => KB,
+            "MB" => MB,
+            "GB" => GB,
+            _ => return Err(err()),
+        };
+        value.checked_mul(unit).map(PageSize).ok_or_else(err)
+    }
+}
+
+impl fmt::Display for PageSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 >= GB {
+            write!(f, "{}GB", self.0 / GB)
+        } else if self.0 >= MB {
+            write!(f, "{}MB", self.0 / MB)
+        } else {
+            write!(f, "{}KB", self.0 / KB)
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Event {
+    pub max: u64,
+}
+
+impl FlatKeyedSetter<u64> for Event {
+    fn new() -> Self {
+        Event {
+            max: 0
+        }
+    }
+
+    fn set(&mut self, s: &str, val: u64) {
+        if s == "max" {
+            self.max = val;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::PageSize;
+    use crate::error::CGroupError;
+
+    #[test]
+    fn page_size_names() {
+        let size = PageSize::from_str("2048kB").unwrap();
+        assert_eq!(size.bytes(), 2 << 20);
+        assert_eq!(size.to_string(), "2MB");
+        assert_eq!(PageSize::from_str("1048576kB").unwrap().to_string(), "1GB");
+        assert_eq!(PageSize::from_str("64kB").unwrap().to_string(), "64KB");
+        assert_eq!(PageSize::from_str("1GB").unwrap(), PageSize::from_bytes(1 << 30));
+        assert!(PageSize::from_str("2048").is_err());
+        assert!(PageSize::from_str("kB").is_err());
+        let overflow = "18446744073709551615GB";
+        assert_eq!(PageSize::from_str(overflow), Err(CGroupError::UnknownFieldErr(overflow.to_string())));
+    }
+}
@@ -1,32 +1,181 @@
-use std::{str::FromStr, collections::HashMap};
+use std::{fmt, str::FromStr, collections::HashMap};
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::{error::{CGroupError, Result}, util::read_nested_keyed_file};
-#[derive(Debug, Eq, PartialEq)]
-pub struct IO<'a> {
-    path: &'a Path
+use crate::{common::Max, device::{BlockDevice, device_name}, error::{CGroupError, Result}, psi::{IOPressure, PressureTrigger, Stall}, util::{CGroupDir, read_nested_keyed_file, read_value, write_single_value}};
+/// The io controller. Per-device setters accept a [`BlockDevice`] in any form, e.g. a
+/// `DeviceNumber`, `"/dev/nvme0n1"`, `"nvme0n1"` or a path such as `"/var/lib/data"`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IO<F = Arc<Path>> {
+    dir: F
 }
 
-impl<'a> IO<'a> {
-    pub fn new(path: &'a Path) -> IO {
+impl IO {
+    pub fn new<P: Into<Arc<Path>>>(path: P) -> IO {
+        IO::with_dir(path.into())
+    }
+}
+
+impl<F: CGroupDir> IO<F> {
+    ///Open the interface files through `dir`, e.g. a [`CGroupFd`](crate::fd::CGroupFd).
+    pub fn with_dir(dir: F) -> Self {
         IO {
-            path
+            dir
         }
     }
 
     pub fn stat(&self) -> Result<HashMap<DeviceNumber, Stat>> {
         let filename = "io.stat";
-        read_nested_keyed_file(&self.path, filename)
+        read_nested_keyed_file(&self.dir, filename)
     }
 
-    ///A read-write nested-keyed file with exists only on the root cgroup.
+    ///io.cost.qos, the QoS settings of the iocost controller. Only exists on the root cgroup.
+    ///
+    ///iocost has no weight file of its own; children are weighted through [`IO::weight`].
     pub fn cost_qos(&self) -> Result<HashMap<DeviceNumber, CostQos>> {
-        let filename = "cost.qos";
-        read_nested_keyed_file(&self.path, filename)
+        let filename = "io.cost.qos";
+        read_nested_keyed_file(&self.dir, filename)
+    }
+
+    ///Write the QoS settings of `device`. With `Ctrl::Auto` only `enable` is written and the
+    ///kernel picks the remaining parameters itself.
+    pub fn set_cost_qos<D: Into<BlockDevice>>(&self, device: D, qos: &CostQos) -> Result<()> {
+        let device = device.into().resolve()?;
+        let filename = "io.cost.qos";
+        write_single_value(&self.dir, filename, format!("{} {}", device, qos))
+    }
+
+    ///Turn iocost on or off for `device`, leaving the other QoS settings alone.
+    pub fn set_cost_enable<D: Into<BlockDevice>>(&self, device: D, enable: bool) -> Result<()> {
+        let device = device.into().resolve()?;
+        let filename = "io.cost.qos";
+        write_single_value(&self.dir, filename, format!("{} enable={}", device, enable as u8))
+    }
+
+    ///io.cost.model, the cost model of the iocost controller. Only exists on the root cgroup.
+    pub fn cost_model(&self) -> Result<HashMap<DeviceNumber, CostModel>> {
+        let filename = "io.cost.model";
+        read_nested_keyed_file(&self.dir, filename)
+    }
+
+    ///Write the cost model of `device`. With `Ctrl::Auto` the kernel's builtin model is restored
+    ///and the coefficients are ignored.
+    pub fn set_cost_model<D: Into<BlockDevice>>(&self, device: D, model: &CostModel) -> Result<()> {
+        let device = device.into().resolve()?;
+        let filename = "io.cost.model";
+        write_single_value(&self.dir, filename, format!("{} {}", device, model))
+    }
+
+    pub fn pressure(&self) -> Result<IOPressure> {
+        let filename = "io.pressure";
+        read_value(&self.dir, filename)
+    }
+
+    ///Throttling limits, keyed by device. Devices without any limit are not listed.
+    pub fn max(&self) -> Result<HashMap<DeviceNumber, IoMax>> {
+        let filename = "io.max";
+        read_nested_keyed_file(&self.dir, filename)
+    }
+
+    ///Set a single limit of a single device. The other limits of the device are left alone;
+    ///pass `Max::Max` to remove the limit.
+    pub fn set_max<D: Into<BlockDevice>>(&self, device: D, limit: IoLimit, value: Max<u64>) -> Result<()> {
+        let device = device.into().resolve()?;
+        let filename = "io.max";
+        write_single_value(&self.dir, filename, format!("{} {}={}", device, limit, value))
+    }
+
+    ///Set all limits of a device at once.
+    pub fn set_max_all<D: Into<BlockDevice>>(&self, device: D, max: &IoMax) -> Result<()> {
+        let device = device.into().resolve()?;
+        let filename = "io.max";
+        write_single_value(&self.dir, filename, format!("{} {}", device, max))
+    }
+
+    ///Remove all limits of a device.
+    pub fn clear_max<D: Into<BlockDevice>>(&self, device: D) -> Result<()> {
+        self.set_max_all(device, &IoMax::unlimited())
+    }
+
+    ///Proportional weights of the io controller
+    pub fn weight(&self) -> Result<IoWeight> {
+        let filename = "io.weight";
+        read_value(&self.dir, filename)
+    }
+
+    ///Set the weight of all devices without an override, in [1, 10000].
+    pub fn set_default_weight(&self, weight: u16) -> Result<()> {
+        let filename = "io.weight";
+        write_single_value(&self.dir, filename, format!("default {}", weight))
+    }
+
+    pub fn set_device_weight<D: Into<BlockDevice>>(&self, device: D, weight: u16) -> Result<()> {
+        let device = device.into().resolve()?;
+        let filename = "io.weight";
+        write_single_value(&self.dir, filename, format!("{} {}", device, weight))
+    }
+
+    ///Remove the override of a device, so that the default weight applies again.
+    pub fn clear_device_weight<D: Into<BlockDevice>>(&self, device: D) -> Result<()> {
+        let device = device.into().resolve()?;
+        let filename = "io.weight";
+        write_single_value(&self.dir, filename, format!("{} default", device))
+    }
+
+    ///Weights of the BFQ IO scheduler
+    pub fn bfq_weight(&self) -> Result<IoWeight> {
+        let filename = "io.bfq.weight";
+        read_value(&self.dir, filename)
+    }
+
+    ///Set the BFQ weight of all devices without an override, in [1, 1000].
+    pub fn set_bfq_default_weight(&self, weight: u16) -> Result<()> {
+        let filename = "io.bfq.weight";
+        write_single_value(&self.dir, filename, format!("default {}", weight))
+    }
+
+    pub fn set_bfq_device_weight<D: Into<BlockDevice>>(&self, device: D, weight: u16) -> Result<()> {
+        let device = device.into().resolve()?;
+        let filename = "io.bfq.weight";
+        write_single_value(&self.dir, filename, format!("{} {}", device, weight))
+    }
+
+    pub fn clear_bfq_device_weight<D: Into<BlockDevice>>(&self, device: D) -> Result<()> {
+        let device = device.into().resolve()?;
+        let filename = "io.bfq.weight";
+        write_single_value(&self.dir, filename, format!("{} default", device))
+    }
+
+    ///io.latency targets, keyed by device. Devices without a target are not listed.
+    pub fn latency(&self) -> Result<HashMap<DeviceNumber, Duration>> {
+        let filename = "io.latency";
+        let targets: HashMap<DeviceNumber, LatencyTarget> = read_nested_keyed_file(&self.dir, filename)?;
+        Ok(targets.into_iter().map(|(device, target)| (device, target.0)).collect())
+    }
+
+    ///Protect the IO latency of this cgroup on `device`. The kernel works with microseconds, so
+    ///`target` is truncated to those.
+    pub fn set_latency<D: Into<BlockDevice>>(&self, device: D, target: Duration) -> Result<()> {
+        let device = device.into().resolve()?;
+        let filename = "io.latency";
+        write_single_value(&self.dir, filename, format!("{} target={}", device, target.as_micros()))
+    }
+
+    ///Remove the latency target of `device`.
+    pub fn clear_latency<D: Into<BlockDevice>>(&self, device: D) -> Result<()> {
+        let device = device.into().resolve()?;
+        let filename = "io.latency";
+        write_single_value(&self.dir, filename, format!("{} target=max", device))
+    }
+
+    ///Register a PSI trigger on io.pressure
+    pub fn pressure_trigger(&self, stall: Stall, threshold: Duration, window: Duration) -> Result<PressureTrigger> {
+        PressureTrigger::open(&self.dir, "io.pressure", stall, threshold, window)
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct DeviceNumber {
     pub maj: u32,
     pub min: u32
@@ -48,27 +197,189 @@ impl FromStr for DeviceNumber {
     }
 }
 
-#[derive(Debug)]
+impl DeviceNumber {
+    ///The kernel name of the device, e.g. `nvme0n1`
+    pub fn name(&self) -> Result<String> {
+        device_name(*self)
+    }
+}
+
+impl fmt::Display for DeviceNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.maj, self.min)
+    }
+}
+
+///One of the limits in io.max
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum IoLimit {
+    ///Read bytes per second
+    Rbps,
+    ///Write bytes per second
+    Wbps,
+    ///Read IO operations per second
+    Riops,
+    ///Write IO operations per second
+    Wiops,
+}
+
+impl fmt::Display for IoLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoLimit::Rbps => f.write_str("rbps"),
+            IoLimit::Wbps => f.write_str("wbps"),
+            IoLimit::Riops => f.write_str("riops"),
+            IoLimit::Wiops => f.write_str("wiops"),
+        }
+    }
+}
+
+///The io.max limits of one device
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct IoMax {
+    pub rbps: Max<u64>,
+    pub wbps: Max<u64>,
+    pub riops: Max<u64>,
+    pub wiops: Max<u64>,
+}
+
+impl IoMax {
+    pub fn unlimited() -> Self {
+        IoMax {
+            rbps: Max::Max,
+            wbps: Max::Max,
+            riops: Max::Max,
+            wiops: Max::Max,
+        }
+    }
+
+    pub fn get(&self, limit: IoLimit) -> Max<u64> {
+        match limit {
+            IoLimit::Rbps => self.rbps,
+            IoLimit::Wbps => self.wbps,
+            IoLimit::Riops => self.riops,
+            IoLimit::Wiops => self.wiops,
+        }
+    }
+}
+
+impl FromStr for IoMax {
+    type Err = CGroupError;
+
+    //rbps=2097152 wbps=max riops=max wiops=120
+    fn from_str(s: &str) -> Result<Self> {
+        let mut max = IoMax::unlimited();
+        for next in s.split_whitespace() {
+            let (key, val) = next.split_once('=')
+                .ok_or(CGroupError::UnknownFieldErr(s.to_string()))?;
+            let val = Max::from_str(val)?;
+            match key {
+                "rbps" => max.rbps = val,
+                "wbps" => max.wbps = val,
+                "riops" => max.riops = val,
+                "wiops" => max.wiops = val,
+                _ => {}
+            }
+        }
+        Ok(max)
+    }
+}
+
+impl fmt::Display for IoMax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rbps={} wbps={} riops={} wiops={}", self.rbps, self.wbps, self.riops, self.wiops)
+    }
+}
+
+///io.weight or io.bfq.weight: a default weight plus per-device overrides
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IoWeight {
+    pub default: u16,
+    pub devices: HashMap<DeviceNumber, u16>,
+}
+
+impl IoWeight {
+    ///The weight that applies to `device`
+    pub fn get(&self, device: &DeviceNumber) -> u16 {
+        self.devices.get(device).copied().unwrap_or(self.default)
+    }
+}
+
+impl FromStr for IoWeight {
+    type Err = CGroupError;
+
+    //default 100
+    //8:16 200
+    fn from_str(s: &str) -> Result<Self> {
+        let err = || CGroupError::UnknownFieldErr(s.to_string());
+        let mut weight = IoWeight {
+            default: 0,
+            devices: HashMap::new(),
+        };
+        for line in s.lines() {
+            let mut splits = line.split_whitespace();
+            let (key, val) = match (splits.next(), splits.next()) {
+                (Some(key), Some(val)) => (key, val),
+                // kernels before 5.4 print only the default for io.bfq.weight
+                (Some(val), None) => ("default", val),
+                _ => continue,
+            };
+            let val = u16::from_str(val).map_err(|_| err())?;
+            if key == "default" {
+                weight.default = val;
+            } else {
+                weight.devices.insert(DeviceNumber::from_str(key)?, val);
+            }
+        }
+        Ok(weight)
+    }
+}
+
+///A latency target in io.latency, in microseconds
+struct LatencyTarget(Duration);
+
+impl FromStr for LatencyTarget {
+    type Err = CGroupError;
+
+    //target=10000
+    fn from_str(s: &str) -> Result<Self> {
+        let target = s.trim().strip_prefix("target=")
+            .and_then(|t| u64::from_str(t).ok())
+            .ok_or(CGroupError::UnknownFieldErr(s.to_string()))?;
+        Ok(LatencyTarget(Duration::from_micros(target)))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Stat {
-    pub rbytes: u32,
-    pub wbytes: u32,
-    pub rios: u32,
-    pub wios: u32,
-    pub dbytes: u32,
-    pub dios: u32
+    pub rbytes: u64,
+    pub wbytes: u64,
+    pub rios: u64,
+    pub wios: u64,
+    pub dbytes: u64,
+    pub dios: u64,
+    ///Queue depth io.latency currently allows. Only reported while io.latency is enabled.
+    pub depth: Option<Max<u64>>,
+    ///Average IO latency as seen by io.latency
+    pub avg_lat: Option<Duration>,
+    ///The io.latency sampling window
+    pub win: Option<Duration>,
 }
 
 impl Stat {
     fn set(&mut self, k: &str, v: &str) -> Result<()> {
-        let v = u32::from_str(v)
-            .map_err(|_| CGroupError::UnknownFieldErr(v.to_string()))?;
+        let err = || CGroupError::UnknownFieldErr(v.to_string());
+        let int = || u64::from_str(v).map_err(|_| err());
         match k {
-            "rbytes" => self.rbytes = v,
-            "wbytes" => self.wbytes = v,
-            "rios" => self.rios = v,
-            "wios" => self.wios = v,
-            "dbytes" => self.dbytes = v,
-            "dios" => self.dios = v,
+            "rbytes" => self.rbytes = int()?,
+            "wbytes" => self.wbytes = int()?,
+            "rios" => self.rios = int()?,
+            "wios" => self.wios = int()?,
+            "dbytes" => self.dbytes = int()?,
+            "dios" => self.dios = int()?,
+            "depth" => self.depth = Some(Max::from_str(v)?),
+            "avg_lat" => self.avg_lat = Some(Duration::from_micros(int()?)),
+            "win" => self.win = Some(Duration::from_millis(int()?)),
             _ => {}
         }
         Ok(())
@@ -78,17 +389,20 @@ impl Stat {
 impl FromStr for Stat {
     type Err = CGroupError;
 
+    //rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0 depth=max avg_lat=0 win=0
     fn from_str(s: &str) -> Result<Self> {
         let mut stat = Stat {
-           rbytes: 0,
+            rbytes: 0,
             wbytes: 0,
             rios: 0,
             wios: 0,
             dbytes: 0,
-            dios: 0
+            dios: 0,
+            depth: None,
+            avg_lat: None,
+            win: None,
         };
-        let mut splits = s.split_whitespace();
-        while let Some(next) = splits.next() {
+        for next in s.split_whitespace() {
             let mut kv = next.split('=');
             let key = kv.next().ok_or(CGroupError::UnknownFieldErr(String::from(s)))?;
             let val = kv.next().ok_or(CGroupError::UnknownFieldErr(String::from(s)))?;
@@ -101,7 +415,7 @@ impl FromStr for Stat {
 /// When “ctrl” is “auto”, the parameters are controlled by the kernel and may change automatically.
 /// Setting “ctrl” to “user” or setting any of the percentile and latency parameters puts it into “user” mode
 /// and disables the automatic changes. The automatic mode can be restored by setting “ctrl” to “auto”.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Ctrl {
     Auto,
     User
@@ -121,32 +435,44 @@ impl FromStr for Ctrl {
         Ok(ctrl)
     }
 }
-#[derive(Debug)]
+
+impl fmt::Display for Ctrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ctrl::Auto => f.write_str("auto"),
+            Ctrl::User => f.write_str("user"),
+        }
+    }
+}
+
+fn parse_field<T: FromStr>(v: &str) -> Result<T> {
+    T::from_str(v).map_err(|_| CGroupError::UnknownFieldErr(v.to_string()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct CostQos {
-    pub enable: u8, //Weight-based control enable
-    pub ctrl: Ctrl, 
+    pub enable: bool, //Weight-based control enable
+    pub ctrl: Ctrl,
     pub rpct: f32, //Read latency percentile [0, 100]
-    pub rlat: u32, //Read latency threshold
+    pub rlat: u64, //Read latency threshold in microseconds
     pub wpct: f32, //Write latency percentile [0, 100]
-    pub wlat: u32, //Write latency threshold
-    pub min: u16, //Minimum scaling percentage [1, 10000]
-    pub max: u16, //Maximum scaling percentage [1, 10000]
+    pub wlat: u64, //Write latency threshold in microseconds
+    pub min: f32, //Minimum scaling percentage [1, 10000]
+    pub max: f32, //Maximum scaling percentage [1, 10000]
 }
 
 impl CostQos {
     fn set(&mut self, k: &str, v: &str) -> Result<()> {
         match k {
-            "enabe" => self.enable = u8::from_str(v).map_err(|_| CGroupError::UnknownFieldErr(v.to_string()))?,
-            "ctrl" => self.ctrl= Ctrl::from_str(v).map_err(|_| CGroupError::UnknownFieldErr(v.to_string()))?,
-            "rpct" => self.rpct= f32::from_str(v).map_err(|_| CGroupError::UnknownFieldErr(v.to_string()))?,
-            "rlat" => self.rlat = u32::from_str(v).map_err(|_| CGroupError::UnknownFieldErr(v.to_string()))?,
-            "wpct" => self.wpct= f32::from_str(v).map_err(|_| CGroupError::UnknownFieldErr(v.to_string()))?,
-            "wlat" => self.wlat= u32::from_str(v).map_err(|_| CGroupError::UnknownFieldErr(v.to_string()))?,
-            "min" => self.min = u16::from_str(v).map_err(|_| CGroupError::UnknownFieldErr(v.to_string()))?,
-            "max" => self.max = u16::from_str(v).map_err(|_| CGroupError::UnknownFieldErr(v.to_string()))?,
-            _ => {
-                return Err(CGroupError::UnknownFieldErr(v.to_string()))
-            }
+            "enable" => self.enable = parse_field::<u8>(v)? != 0,
+            "ctrl" => self.ctrl = Ctrl::from_str(v)?,
+            "rpct" => self.rpct = parse_field(v)?,
+            "rlat" => self.rlat = parse_field(v)?,
+            "wpct" => self.wpct = parse_field(v)?,
+            "wlat" => self.wlat = parse_field(v)?,
+            "min" => self.min = parse_field(v)?,
+            "max" => self.max = parse_field(v)?,
+            _ => {}
         }
         Ok(())
     }
@@ -155,19 +481,19 @@ impl CostQos {
 impl FromStr for CostQos {
     type Err = CGroupError;
 
+    //enable=1 ctrl=user rpct=95.00 rlat=5000 wpct=95.00 wlat=5000 min=50.00 max=150.00
     fn from_str(s: &str) -> Result<Self> {
         let mut cost_qos = CostQos {
-            enable: 0,
+            enable: false,
             ctrl: Ctrl::Auto,
             rpct: 0.0,
             rlat: 0,
             wpct: 0.0,
             wlat: 0,
-            min: 0,
-            max: 0,
+            min: 0.0,
+            max: 0.0,
         };
-        let mut splits = s.split_whitespace();
-        while let Some(next) = splits.next() {
+        for next in s.split_whitespace() {
             let mut kv = next.split('=');
             let key = kv.next().ok_or(CGroupError::UnknownFieldErr(s.to_string()))?;
             let val = kv.next().ok_or(CGroupError::UnknownFieldErr(s.to_string()))?;
@@ -175,4 +501,135 @@ impl FromStr for CostQos {
         }
         Ok(cost_qos)
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for CostQos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // any parameter switches the kernel to user mode, so only write them in that mode
+        write!(f, "enable={} ctrl={}", self.enable as u8, self.ctrl)?;
+        if self.ctrl == Ctrl::User {
+            write!(f, " rpct={:.2} rlat={} wpct={:.2} wlat={} min={:.2} max={:.2}",
+                   self.rpct, self.rlat, self.wpct, self.wlat, self.min, self.max)?;
+        }
+        Ok(())
+    }
+}
+
+///The linear cost model of io.cost.model, the only model the kernel implements.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CostModel {
+    pub ctrl: Ctrl,
+    pub rbps: u64, //Maximum sequential read bytes per second
+    pub rseqiops: u64, //Maximum 4k sequential read IOs per second
+    pub rrandiops: u64, //Maximum 4k random read IOs per second
+    pub wbps: u64, //Maximum sequential write bytes per second
+    pub wseqiops: u64, //Maximum 4k sequential write IOs per second
+    pub wrandiops: u64, //Maximum 4k random write IOs per second
+}
+
+impl FromStr for CostModel {
+    type Err = CGroupError;
+
+    //ctrl=auto model=linear rbps=... rseqiops=... rrandiops=... wbps=... wseqiops=... wrandiops=...
+    fn from_str(s: &str) -> Result<Self> {
+        let mut model = CostModel {
+            ctrl: Ctrl::Auto,
+            rbps: 0,
+            rseqiops: 0,
+            rrandiops: 0,
+            wbps: 0,
+            wseqiops: 0,
+            wrandiops: 0,
+        };
+        for next in s.split_whitespace() {
+            let (key, val) = next.split_once('=')
+                .ok_or(CGroupError::UnknownFieldErr(s.to_string()))?;
+            match key {
+                "ctrl" => model.ctrl = Ctrl::from_str(val)?,
+                "model" if val != "linear" => return Err(CGroupError::UnknownFieldErr(val.to_string())),
+                "rbps" => model.rbps = parse_field(val)?,
+                "rseqiops" => model.rseqiops = parse_field(val)?,
+                "rrandiops" => model.rrandiops = parse_field(val)?,
+                "wbps" => model.wbps = parse_field(val)?,
+                "wseqiops" => model.wseqiops = parse_field(val)?,
+                "wrandiops" => model.wrandiops = parse_field(val)?,
+                _ => {}
+            }
+        }
+        Ok(model)
+    }
+}
+
+impl fmt::Display for CostModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ctrl={}", self.ctrl)?;
+        if self.ctrl == Ctrl::User {
+            write!(f, " model=linear rbps={} rseqiops={} rrandiops={} wbps={} wseqiops={} wrandiops={}",
+                   self.rbps, self.rseqiops, self.rrandiops, self.wbps, self.wseqiops, self.wrandiops)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr, time::Duration};
+
+    use super::{CostModel, CostQos, Ctrl, DeviceNumber, IoLimit, IoMax, IoWeight, LatencyTarget, Stat};
+    use crate::{common::Max, util::parse_nested_keyed};
+
+    #[test]
+    fn parse_io_max() {
+        let max: HashMap<DeviceNumber, IoMax> =
+            parse_nested_keyed("8:16 rbps=2097152 wbps=max riops=max wiops=120\n").unwrap();
+        let device = DeviceNumber { maj: 8, min: 16 };
+        assert_eq!(device.to_string(), "8:16");
+        let entry = max[&device];
+        assert_eq!(entry.rbps, Max::Val(2097152));
+        assert_eq!(entry.get(IoLimit::Wbps), Max::Max);
+        assert_eq!(entry.get(IoLimit::Wiops), Max::Val(120));
+        assert_eq!(entry.to_string(), "rbps=2097152 wbps=max riops=max wiops=120");
+        assert_eq!(IoMax::unlimited().to_string(), "rbps=max wbps=max riops=max wiops=max");
+    }
+
+    #[test]
+    fn parse_io_weight() {
+        let weight = IoWeight::from_str("default 100\n8:16 200\n").unwrap();
+        let device = DeviceNumber { maj: 8, min: 16 };
+        assert_eq!(weight.default, 100);
+        assert_eq!(weight.get(&device), 200);
+        assert_eq!(weight.get(&DeviceNumber { maj: 8, min: 0 }), 100);
+        assert_eq!(IoWeight::from_str("500\n").unwrap().default, 500);
+        assert!(IoWeight::from_str("8:16 heavy\n").is_err());
+    }
+
+    #[test]
+    fn parse_io_latency() {
+        let targets: HashMap<DeviceNumber, LatencyTarget> = parse_nested_keyed("8:16 target=10000\n").unwrap();
+        assert_eq!(targets[&DeviceNumber { maj: 8, min: 16 }].0, Duration::from_millis(10));
+
+        let stat = Stat::from_str("rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0\n").unwrap();
+        assert_eq!(stat.wbytes, 314773504);
+        assert_eq!(stat.depth, None);
+        let stat = Stat::from_str("rbytes=0 wbytes=0 rios=0 wios=0 dbytes=0 dios=0 depth=max avg_lat=250 win=100").unwrap();
+        assert_eq!(stat.depth, Some(Max::Max));
+        assert_eq!(stat.avg_lat, Some(Duration::from_micros(250)));
+        assert_eq!(stat.win, Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn parse_io_cost() {
+        let qos = CostQos::from_str("enable=1 ctrl=user rpct=95.00 rlat=5000 wpct=95.00 wlat=5000 min=50.00 max=150.00").unwrap();
+        assert!(qos.enable);
+        assert_eq!((qos.rlat, qos.min, qos.max), (5000, 50.0, 150.0));
+        assert_eq!(qos.to_string(), "enable=1 ctrl=user rpct=95.00 rlat=5000 wpct=95.00 wlat=5000 min=50.00 max=150.00");
+        let auto = CostQos { ctrl: Ctrl::Auto, ..qos };
+        assert_eq!(auto.to_string(), "enable=1 ctrl=auto");
+
+        let content = "ctrl=user model=linear rbps=174019176 rseqiops=41708 rrandiops=370 wbps=178075866 wseqiops=42705 wrandiops=378";
+        let model = CostModel::from_str(content).unwrap();
+        assert_eq!((model.ctrl, model.rbps, model.wrandiops), (Ctrl::User, 174019176, 378));
+        assert_eq!(model.to_string(), content);
+        assert!(CostModel::from_str("ctrl=auto model=quadratic").is_err());
+    }
+}
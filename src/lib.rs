@@ -1,13 +1,23 @@
-mod manager;
+pub mod manager;
 pub mod error;
 pub mod util;
 pub mod controller;
 pub mod cgroup;
 pub mod cpu;
+pub mod cpuset;
 pub mod psi;
 pub mod memory;
+pub mod hugetlb;
 pub mod common;
 pub mod io;
+pub mod device;
+pub mod pids;
+pub mod rdma;
+pub mod misc;
+pub mod mount;
+pub mod walk;
+pub mod fd;
+pub mod watch;
 
 /// This is a native rust lib for (cgroup V2)[https://www.kernel.org/doc/html/latest/admin-guide/cgroup-v2.html].
 /// The default base path of cgroup should be **/sys/fs/cgroup**. Or you can mount a new fs if you
@@ -35,7 +45,6 @@ pub mod io;
 /// # sudo echo "+cpuset +cpu +io +memory +pids" > cgroup.subtree_control
 /// ```
 /// TODO
-
 pub trait FlatKeyedSetter<T> {
     fn new() -> Self;
     fn set(&mut self, s: &str, val: T);
@@ -1,6 +1,15 @@
 use std::{
+    env,
     fs,
-    path::PathBuf,
+    io::ErrorKind,
+    ops::Deref,
+    path::{
+        Component,
+        Path,
+        PathBuf,
+    },
+    thread,
+    time::Duration,
 };
 
 use users::get_current_uid;
@@ -11,6 +20,11 @@ use crate::{
         CGroupError,
         Result,
     },
+    mount::Mounts,
+    util::{
+        is_cgroup2_dir,
+        read_file_into_string,
+    },
 };
 
 #[derive(Debug)]
@@ -18,7 +32,79 @@ pub struct Manager {
     path: PathBuf
 }
 
+/// Environment variable read by [`Manager::from_env`].
+pub const ROOT_ENV: &str = "CGROUPV2_ROOT";
+
+const DRAIN_RETRIES: u32 = 100;
+const DRAIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What [`Manager::delete_recursive`] does with processes still living in the cgroups it removes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DeletePolicy {
+    /// Move them into the cgroup of this manager. Because of the no-internal-process rule the
+    /// kernel refuses that with `ResourceBusy` as soon as the cgroup has domain controllers
+    /// enabled in its subtree_control, so in practice this only works from the root cgroup.
+    /// Every process is moved before the first cgroup is removed, so a refused migration leaves
+    /// the hierarchy intact.
+    Migrate,
+    /// Kill them through cgroup.kill.
+    Kill,
+    /// Fail with `ResourceBusy` and leave the hierarchy untouched.
+    Fail,
+}
+
 impl Manager {
+    /// Open an existing cgroup directory as the root of this manager.
+    /// Fails with `NotCGroup2Err` if `path` is not a directory on a cgroup2 filesystem.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Manager> {
+        let path = path.as_ref();
+        if !is_cgroup2_dir(path)? {
+            return Err(CGroupError::NotCGroup2Err(path.display().to_string()));
+        }
+        Ok(Manager {
+            path: PathBuf::from(path)
+        })
+    }
+
+    /// Open the cgroup named by the `CGROUPV2_ROOT` environment variable.
+    pub fn from_env() -> Result<Manager> {
+        match env::var_os(ROOT_ENV) {
+            Some(path) => Self::open(path),
+            None => Err(CGroupError::EnvVarErr(ROOT_ENV.to_string()))
+        }
+    }
+
+    /// Open the root of the cgroup2 hierarchy found in /proc/self/mountinfo. On hybrid hosts
+    /// this is the unified mount, usually /sys/fs/cgroup/unified.
+    pub fn discover() -> Result<Manager> {
+        let mounts = Mounts::read()?;
+        let mount = mounts.unified().ok_or(CGroupError::MountNotFoundErr)?;
+        Self::open(&mount.mount_point)
+    }
+
+    /// Open the cgroup the calling process belongs to.
+    pub fn current() -> Result<CGroup> {
+        Self::from_proc_cgroup(Path::new("/proc/self/cgroup"))
+    }
+
+    /// Open the cgroup process `pid` belongs to.
+    pub fn for_pid(pid: u32) -> Result<CGroup> {
+        Self::from_proc_cgroup(&PathBuf::from(format!("/proc/{}/cgroup", pid)))
+    }
+
+    fn from_proc_cgroup(proc_cgroup: &Path) -> Result<CGroup> {
+        let content = read_file_into_string(proc_cgroup)?;
+        let cgroup_path = parse_proc_cgroup(&content)?;
+        let mounts = Mounts::read()?;
+        let mount = mounts.unified().ok_or(CGroupError::MountNotFoundErr)?;
+        let relative = relative_to_mount(cgroup_path, &mount.root)?;
+        Ok(Self::open(mount.mount_point.join(relative))?.cgroup())
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
     pub fn cgroup(&self) -> CGroup {
         CGroup::new(self.path.as_path())
     }
@@ -41,10 +127,59 @@ impl Manager {
     pub fn delete_child(&self, cgroup_name: &str) -> Result<()> {
         let mut path = PathBuf::from(&self.path);
         path.push(cgroup_name);
-        return match fs::remove_dir(path) {
+        match fs::remove_dir(path) {
             Ok(()) => Ok(()),
             Err(err) => Err(CGroupError::FSErr(err.kind()))
-        };
+        }
+    }
+
+    /// Create a child cgroup that is removed again, together with its descendants, when the
+    /// returned guard is dropped. Remaining processes are killed unless another policy is set
+    /// with [`ScopedCGroup::with_policy`].
+    pub fn scoped_child(&self, cgroup_name: &str) -> Result<ScopedCGroup> {
+        let child = self.new_child(cgroup_name)?;
+        Ok(ScopedCGroup {
+            parent: Manager {
+                path: self.path.clone()
+            },
+            name: cgroup_name.to_string(),
+            child,
+            policy: DeletePolicy::Kill,
+            armed: true,
+        })
+    }
+
+    /// Remove a child cgroup together with all of its descendants, leaves first. Processes left
+    /// in any of them are handled according to `policy`.
+    pub fn delete_recursive(&self, cgroup_name: &str, policy: DeletePolicy) -> Result<()> {
+        let path = self.path.join(cgroup_name);
+        let c_group = CGroup::new(path.as_path());
+        let mut paths = c_group
+            .descendants()
+            .map(|entry| entry.map(|e| e.path))
+            .collect::<Result<Vec<PathBuf>>>()?;
+        // a pre-order walk reversed lists every cgroup before its parent
+        paths.reverse();
+        paths.push(path.clone());
+
+        match policy {
+            DeletePolicy::Fail => {
+                if c_group.events()?.populated {
+                    return Err(CGroupError::FSErr(ErrorKind::ResourceBusy));
+                }
+            }
+            DeletePolicy::Kill => c_group.kill(DRAIN_INTERVAL * DRAIN_RETRIES)?,
+            DeletePolicy::Migrate => {}
+        }
+        let parent = self.cgroup();
+        // empty the whole subtree before the first rmdir, so that a failed migration leaves it intact
+        for path in &paths {
+            drain(&CGroup::new(path.as_path()), &parent, policy)?;
+        }
+        for path in paths {
+            remove_dir_retry(&path)?;
+        }
+        Ok(())
     }
 }
 
@@ -52,18 +187,123 @@ const DEFAULT_MOUNT_POINT: &str = "/sys/fs/cgroup/";
 
 impl Default for Manager {
     fn default() -> Self {
-        let mount_point = DEFAULT_MOUNT_POINT;
-        let path = PathBuf::from(get_delegate_path(mount_point));
+        let mount_point = Mounts::read()
+            .ok()
+            .and_then(|mounts| mounts.unified().map(|m| m.mount_point.clone()))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_MOUNT_POINT));
+        let path = PathBuf::from(get_delegate_path(&mount_point));
         Manager {
             path
         }
     }
 }
 
-fn get_delegate_path(mount_point: &str) -> String {
+/// A child cgroup that is removed when dropped. Derefs to the [`Manager`] of the child.
+#[derive(Debug)]
+pub struct ScopedCGroup {
+    parent: Manager,
+    name: String,
+    child: Manager,
+    policy: DeletePolicy,
+    armed: bool,
+}
+
+impl ScopedCGroup {
+    /// Decide what happens to processes still in the cgroup when the guard is dropped.
+    pub fn with_policy(mut self, policy: DeletePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Remove the cgroup now and report failures instead of ignoring them in drop.
+    pub fn delete(mut self) -> Result<()> {
+        self.armed = false;
+        self.parent.delete_recursive(&self.name, self.policy)
+    }
+
+    /// Keep the cgroup and hand back a plain [`Manager`] for it.
+    pub fn release(mut self) -> Manager {
+        self.armed = false;
+        Manager {
+            path: self.child.path.clone()
+        }
+    }
+}
+
+impl Deref for ScopedCGroup {
+    type Target = Manager;
+
+    fn deref(&self) -> &Manager {
+        &self.child
+    }
+}
+
+impl Drop for ScopedCGroup {
+    fn drop(&mut self) {
+        if self.armed {
+            let _ = self.parent.delete_recursive(&self.name, self.policy);
+        }
+    }
+}
+
+/// Wait until `c_group` reports `populated 0`, moving stragglers into `parent` on every round
+/// if processes are migrated.
+fn drain(c_group: &CGroup, parent: &CGroup, policy: DeletePolicy) -> Result<()> {
+    for _ in 0..DRAIN_RETRIES {
+        let events = match c_group.events() {
+            Ok(events) => events,
+            Err(CGroupError::FSErr(ErrorKind::NotFound)) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        if !events.populated {
+            return Ok(());
+        }
+        if policy == DeletePolicy::Migrate {
+            c_group.migrate_all_to(parent)?;
+        }
+        thread::sleep(DRAIN_INTERVAL);
+    }
+    Err(CGroupError::FSErr(ErrorKind::ResourceBusy))
+}
+
+/// rmdir can still fail with EBUSY for a short while after the last process left.
+fn remove_dir_retry(path: &Path) -> Result<()> {
+    for _ in 0..DRAIN_RETRIES {
+        match fs::remove_dir(path) {
+            Ok(()) => return Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) if err.kind() == ErrorKind::ResourceBusy => thread::sleep(DRAIN_INTERVAL),
+            Err(err) => return Err(CGroupError::FSErr(err.kind())),
+        }
+    }
+    Err(CGroupError::FSErr(ErrorKind::ResourceBusy))
+}
+
+/// Turn a path from /proc/<pid>/cgroup into one relative to a mount of the subtree at
+/// `mount_root`. Fails with `NotVisibleErr` if the cgroup lies outside of that subtree, or
+/// outside of the reader's cgroup namespace, which the kernel reports as `/../..`.
+fn relative_to_mount<'a>(cgroup_path: &'a Path, mount_root: &Path) -> Result<&'a Path> {
+    let not_visible = || CGroupError::NotVisibleErr(cgroup_path.display().to_string());
+    let relative = cgroup_path.strip_prefix(mount_root).map_err(|_| not_visible())?;
+    if relative.components().any(|c| c == Component::ParentDir) {
+        return Err(not_visible());
+    }
+    Ok(relative)
+}
+
+/// Take the cgroup2 entry "0::/path" out of a /proc/<pid>/cgroup file.
+fn parse_proc_cgroup(content: &str) -> Result<&Path> {
+    content
+        .split('\n')
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(Path::new)
+        .ok_or_else(|| CGroupError::UnknownFieldErr(content.to_string()))
+}
+
+fn get_delegate_path(mount_point: &Path) -> String {
     let uid = get_current_uid();
     let delegate_path = format!("{}/user.slice/user-{}.slice/user@{}.service/",
-                                mount_point, uid, uid);
+                                mount_point.display(), uid, uid);
     delegate_path
 }
 
@@ -78,6 +318,53 @@ mod tests {
     use crate::psi::{CPUPressure, PSIMetric};
     use crate::memory::{Event, SwapEvent};
     use crate::FlatKeyedSetter;
+    use crate::error::CGroupError;
+    use crate::manager::{DeletePolicy, ROOT_ENV, parse_proc_cgroup, relative_to_mount};
+    use std::path::Path;
+    use rusty_fork::rusty_fork_test;
+
+    #[test]
+    fn proc_cgroup() {
+        let content = "0::/user.slice/user-1000.slice/session-2.scope\n";
+        assert_eq!(parse_proc_cgroup(content), Ok(Path::new("/user.slice/user-1000.slice/session-2.scope")));
+        let hybrid = "12:memory:/user.slice\n1:name=systemd:/user.slice/session-2.scope\n0::/user.slice/session-2.scope\n";
+        assert_eq!(parse_proc_cgroup(hybrid), Ok(Path::new("/user.slice/session-2.scope")));
+        let legacy = "12:memory:/user.slice\n1:name=systemd:/user.slice/session-2.scope\n";
+        assert!(parse_proc_cgroup(legacy).is_err());
+    }
+
+    #[test]
+    fn relative_proc_cgroup() {
+        let cgroup = Path::new("/user.slice/session-2.scope");
+        assert_eq!(relative_to_mount(cgroup, Path::new("/")), Ok(Path::new("user.slice/session-2.scope")));
+        assert_eq!(relative_to_mount(cgroup, Path::new("/user.slice")), Ok(Path::new("session-2.scope")));
+        let other = Path::new("/system.slice/x.service");
+        assert_eq!(relative_to_mount(other, Path::new("/user.slice")),
+                   Err(CGroupError::NotVisibleErr("/system.slice/x.service".to_string())));
+        assert!(relative_to_mount(Path::new("/../../x"), Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn open_rejects_non_cgroup2_dir() {
+        let result = Manager::open("/tmp");
+        assert_eq!(result.unwrap_err(), CGroupError::NotCGroup2Err("/tmp".to_string()));
+        let result = Manager::open("/path/does/not/exist");
+        assert_eq!(result.unwrap_err(), CGroupError::FSErr(std::io::ErrorKind::NotFound));
+    }
+
+    rusty_fork_test! {
+        // runs in its own process, so that changing the environment cannot race other tests
+        #[test]
+        fn from_env_validates_root() {
+            std::env::remove_var(ROOT_ENV);
+            let result = Manager::from_env();
+            assert_eq!(result.unwrap_err(), CGroupError::EnvVarErr(ROOT_ENV.to_string()));
+            std::env::set_var(ROOT_ENV, "/tmp");
+            let result = Manager::from_env();
+            assert_eq!(result.unwrap_err(), CGroupError::NotCGroup2Err("/tmp".to_string()));
+            std::env::remove_var(ROOT_ENV);
+        }
+    }
 
     #[test]
     fn enabled_controllers() {
@@ -101,7 +388,9 @@ mod tests {
     #[test]
     fn new_child() {
         let manager = Manager::default();
-        let child = manager.new_child("cgv2");
+        let cgroup_name = "cgv2";
+        let _ = manager.delete_recursive(cgroup_name, DeletePolicy::Kill);
+        let child = manager.scoped_child(cgroup_name);
         assert!(child.is_ok());
         let child = child.unwrap();
         let c_group = child.cgroup();
@@ -137,23 +426,117 @@ mod tests {
         assert_eq!(result, Ok(Max::Val(16)));
         let result = c_group.stat();
         assert_eq!(result, Ok(CGroupStat { nr_descendants: 0, nr_dying_descendants: 0 }));
-        let result = c_group.freeze();
+        let result = c_group.freeze_state();
         assert_eq!(result, Ok(Freeze(false)));
         let result = c_group.set_freeze();
         assert!(result.is_ok());
+        let result = c_group.freeze_state();
+        assert_eq!(result, Ok(Freeze(true)));
+        let result = c_group.wait_frozen(std::time::Duration::from_secs(1));
+        assert!(result.is_ok());
+        let result = c_group.events();
+        assert_eq!(result, Ok(CGroupEvent { populated: false, frozen: true }));
+        let result = c_group.thaw();
+        assert!(result.is_ok());
+        let result = c_group.freeze_state();
+        assert_eq!(result, Ok(Freeze(false)));
         let result = c_group.freeze();
+        assert!(result.is_ok());
+        let result = c_group.freeze_state();
         assert_eq!(result, Ok(Freeze(true)));
-        let result = manager.delete_child("cgv2");
+        let result = c_group.thaw();
+        assert!(result.is_ok());
+        let result = child.delete();
         assert!(result.is_ok())
     }
 
+    #[test]
+    fn delete_recursive() {
+        let manager = Manager::default();
+        let cgroup_name = "cgv2-recursive";
+        let _ = manager.delete_recursive(cgroup_name, DeletePolicy::Fail);
+        let child = manager.new_child(cgroup_name).unwrap();
+        let grandchild = child.new_child("nested").unwrap();
+        grandchild.new_child("leaf").unwrap();
+        let result = manager.delete_child(cgroup_name);
+        assert!(result.is_err());
+        let result = manager.delete_recursive(cgroup_name, DeletePolicy::Kill);
+        assert_eq!(result, Ok(()));
+        assert!(!child.path().exists());
+    }
+
+    #[test]
+    fn scoped_child() {
+        let manager = Manager::default();
+        let cgroup_name = "cgv2-scoped";
+        let _ = manager.delete_recursive(cgroup_name, DeletePolicy::Kill);
+        let path = {
+            let child = manager.scoped_child(cgroup_name).unwrap();
+            child.new_child("nested").unwrap();
+            assert!(child.path().exists());
+            child.path().to_path_buf()
+        };
+        assert!(!path.exists());
+        let child = manager.scoped_child(cgroup_name).unwrap().release();
+        assert!(child.path().exists());
+        assert_eq!(manager.delete_child(cgroup_name), Ok(()));
+    }
+
+    #[test]
+    fn migrate_all() {
+        let manager = Manager::default();
+        let _ = manager.delete_recursive("cgv2-from", DeletePolicy::Kill);
+        let _ = manager.delete_recursive("cgv2-to", DeletePolicy::Kill);
+        let from = manager.scoped_child("cgv2-from").unwrap();
+        let to = manager.scoped_child("cgv2-to").unwrap();
+        let mut child = std::process::Command::new("sleep").arg("60").spawn().unwrap();
+        from.cgroup().add_pid(child.id()).unwrap();
+
+        let result = from.cgroup().migrate_all_to_frozen(&to.cgroup());
+        assert_eq!(result, Ok(1));
+        assert_eq!(from.cgroup().procs(), Ok(vec![]));
+        assert_eq!(from.cgroup().freeze_state(), Ok(Freeze(false)));
+        assert_eq!(to.cgroup().procs(), Ok(vec![child.id() as i32]));
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn kill() {
+        let manager = Manager::default();
+        let _ = manager.delete_recursive("cgv2-kill", DeletePolicy::Kill);
+        let c_group = manager.scoped_child("cgv2-kill").unwrap();
+        let nested = c_group.new_child("nested").unwrap();
+        let mut child = std::process::Command::new("sleep").arg("60").spawn().unwrap();
+        nested.cgroup().add_pid(child.id()).unwrap();
+        assert_eq!(c_group.cgroup().events().map(|e| e.populated), Ok(true));
+
+        let result = c_group.cgroup().kill(std::time::Duration::from_secs(5));
+        assert_eq!(result, Ok(()));
+        assert_eq!(c_group.cgroup().events().map(|e| e.populated), Ok(false));
+        assert!(child.wait().is_ok());
+    }
+
+    #[test]
+    fn pids_test() {
+        let manager = Manager::default();
+        let cgroup_name = "mycgv2-pids";
+        let _ = manager.delete_recursive(cgroup_name, DeletePolicy::Kill);
+        let child = manager.scoped_child(cgroup_name).unwrap();
+        let pids = child.cgroup().pids();
+        assert_eq!(pids.max(), Ok(crate::common::Max::Max));
+        assert_eq!(pids.set_max(crate::common::Max::Val(64)), Ok(()));
+        assert_eq!(pids.max(), Ok(crate::common::Max::Val(64)));
+        assert_eq!(pids.current(), Ok(0));
+        assert_eq!(pids.events(), Ok(crate::pids::Event { max: 0 }));
+    }
+
     #[test]
     fn cpu_test() {
         let manager = Manager::default();
-        let cgroup_name = "mycgv2";
-        manager.delete_child(cgroup_name);
-        let result = manager.new_child(cgroup_name);
-        let child = result.unwrap();
+        let cgroup_name = "mycgv2-cpu";
+        let _ = manager.delete_recursive(cgroup_name, DeletePolicy::Kill);
+        let child = manager.scoped_child(cgroup_name).unwrap();
         let c_group = child.cgroup();
         let cpu = c_group.cpu();
         let stat = cpu.stat();
@@ -178,10 +561,10 @@ mod tests {
 
         let max = cpu.max();
         assert_eq!(max, Ok(CPUMax{max: crate::common::Max::Max, period: Some(100000)}));
-        let set_max = cpu.set_max(u32::max_value(), None);
+        let set_max = cpu.set_max(u32::MAX, None);
         assert_eq!(set_max, Ok(()));
         let max = cpu.max();
-        assert_eq!(max, Ok(CPUMax{max: crate::common::Max::Val(u32::max_value()), period: Some(100000)}));
+        assert_eq!(max, Ok(CPUMax{max: crate::common::Max::Val(u32::MAX), period: Some(100000)}));
 
         let pressure = cpu.pressure();
         let expect = CPUPressure {
@@ -191,19 +574,24 @@ mod tests {
                 avg60: 0.0,
                 avg300: 0.0,
                 total: 0
-            }
+            },
+            full: Some(PSIMetric {
+                key: "full".to_string(),
+                avg10: 0.0,
+                avg60: 0.0,
+                avg300: 0.0,
+                total: 0
+            }),
         };
         assert_eq!(pressure, Ok(expect));
-        manager.delete_child(cgroup_name);
     }
 
     #[test]
     fn memory_test() {
         let manager = Manager::default();
-        let cgroup_name = "mycgv2";
-        let _ = manager.delete_child(cgroup_name);
-        let result = manager.new_child(cgroup_name);
-        let child = result.unwrap();
+        let cgroup_name = "mycgv2-memory";
+        let _ = manager.delete_recursive(cgroup_name, DeletePolicy::Kill);
+        let child = manager.scoped_child(cgroup_name).unwrap();
         let c_group = child.cgroup();
         let memory = c_group.memory();
         let result = memory.current();
@@ -285,27 +673,24 @@ mod tests {
             },
         };
         assert_eq!(pressure, Ok(expect));
-
-        manager.delete_child(cgroup_name);
     }
 
     #[test]
     fn cgroup_io_test() -> Result<()> {
         
         let manager = Manager::default();
-        let cgroup_name = "mycgv2";
-        let _ = manager.delete_child(cgroup_name);
-        let child= manager.new_child(cgroup_name)?;
+        let cgroup_name = "mycgv2-io";
+        let _ = manager.delete_recursive(cgroup_name, DeletePolicy::Kill);
+        let child = manager.scoped_child(cgroup_name)?;
         let c_group = child.cgroup();
         let io = c_group.io();
 
 
-        let stat = io.stat()?;
+        let _stat = io.stat()?;
         let root= manager.cgroup();
         let root_io = root.io();
         let cost_qos = root_io.cost_qos()?;
         dbg!(cost_qos);
-        manager.delete_child(cgroup_name);
 
         Ok(())
     }
@@ -1,136 +1,150 @@
 use std::{
     path::Path,
+    sync::Arc,
+    time::Duration,
 };
 
 use crate::{FlatKeyedSetter, error::{
         Result,
-    }, util::{read_flat_keyed_file, read_nested_keyed_file_to_map, read_single_value, write_single_value}};
+    }, util::{CGroupDir, read_flat_keyed_file, read_nested_keyed_file_to_map, read_single_value, write_single_value}};
 use crate::common::Max;
 use std::collections::HashMap;
 use crate::util::{read_flat_keyed_file_map, read_value};
-use crate::psi::MemoryPressure;
+use crate::psi::{MemoryPressure, PressureTrigger, Stall};
 
-#[derive(Debug, Eq, PartialEq)]
-pub struct Memory<'a> {
-    path: &'a Path
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Memory<F = Arc<Path>> {
+    dir: F
+}
+
+impl Memory {
+    pub fn new<P: Into<Arc<Path>>>(path: P) -> Self {
+        Memory::with_dir(path.into())
+    }
 }
 
-impl<'a> Memory<'a> {
-    pub fn new(path: &'a Path) -> Self {
+impl<F: CGroupDir> Memory<F> {
+    ///Open the interface files through `dir`, e.g. a [`CGroupFd`](crate::fd::CGroupFd).
+    pub fn with_dir(dir: F) -> Self {
         Memory {
-            path
+            dir
         }
     }
 
     pub fn current(&self) -> Result<u64> {
         let filename = "memory.current";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
 
     }
 
     pub fn min(&self) -> Result<u64> {
         let filename = "memory.min";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
     }
 
     pub fn set_min(&self, min: u64) -> Result<()> {
         let filename = "memory.min";
-        write_single_value(&self.path, filename, min)
+        write_single_value(&self.dir, filename, min)
     }
 
     pub fn low(&self) -> Result<u64> {
         let filename = "memory.low";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
     }
 
     pub fn set_low(&self, low: u64) -> Result<()> {
         let filename = "memory.low";
-        write_single_value(&self.path, filename, low)
+        write_single_value(&self.dir, filename, low)
     }
 
     pub fn high(&self) -> Result<u64> {
         let filename = "memory.high";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
     }
 
     pub fn set_high(&self, high: u64) -> Result<()> {
         let filename = "memory.high";
-        write_single_value(&self.path, filename, high)
+        write_single_value(&self.dir, filename, high)
     }
 
     pub fn max(&self) -> Result<Max> {
         let filename = "memory.max";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
     }
 
     pub fn set_max(&self, max: u64) -> Result<()> {
         let filename = "memory.max";
-        write_single_value(&self.path, filename, max)
+        write_single_value(&self.dir, filename, max)
     }
 
     pub fn oom_group(&self) -> Result<u8> {
         let filename = "memory.oom.group";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
     }
 
     pub fn set_oom_group(&self, i: u8) -> Result<()> {
         let filename = "memory.oom.group";
-        write_single_value(&self.path, filename, i)
+        write_single_value(&self.dir, filename, i)
     }
 
     pub fn events(&self) -> Result<Event> {
         let filename = "memory.events";
-        read_flat_keyed_file(&self.path, filename)
+        read_flat_keyed_file(&self.dir, filename)
     }
 
     pub fn events_local(&self) -> Result<Event> {
         let filename = "memory.events.local";
-        read_flat_keyed_file(&self.path, filename)
+        read_flat_keyed_file(&self.dir, filename)
     }
 
     pub fn stat(&self) -> Result<HashMap<String, u64>> {
         let filename = "memory.stat";
-        read_flat_keyed_file_map(&self.path, filename)
+        read_flat_keyed_file_map(&self.dir, filename)
     }
 
     pub fn numa_stat(&self) -> Result<HashMap<String, HashMap<String, u32>>> {
         let filename = "memory.numa_stat";
-        read_nested_keyed_file_to_map(&self.path, filename)
+        read_nested_keyed_file_to_map(&self.dir, filename)
     }
 
     pub fn swap_current(&self) -> Result<u64> {
         let filename = "memory.swap.current";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
     }
 
     pub fn swap_high(&self) -> Result<Max> {
         let filename = "memory.swap.high";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
     }
 
     pub fn set_swap_high(&self, max: u32) -> Result<()> {
         let filename = "memory.swap.high";
-        write_single_value(&self.path, filename, max)
+        write_single_value(&self.dir, filename, max)
     }
 
     pub fn swap_max(&self) -> Result<Max> {
         let filename = "memory.swap.max";
-        read_single_value(&self.path, filename)
+        read_single_value(&self.dir, filename)
     }
 
     pub fn set_swap_max(&self, max: u32) -> Result<()> {
         let filename = "memory.swap.max";
-        write_single_value(&self.path, filename, max)
+        write_single_value(&self.dir, filename, max)
     }
 
     pub fn swap_events(&self) -> Result<SwapEvent> {
         let filename = "memory.swap.events";
-        read_flat_keyed_file(&self.path, filename)
+        read_flat_keyed_file(&self.dir, filename)
     }
 
     pub fn pressure(&self) -> Result<MemoryPressure> {
         let filename = "memory.pressure";
-        read_value(&self.path, filename)
+        read_value(&self.dir, filename)
+    }
+
+    ///Register a PSI trigger on memory.pressure
+    pub fn pressure_trigger(&self, stall: Stall, threshold: Duration, window: Duration) -> Result<PressureTrigger> {
+        PressureTrigger::open(&self.dir, "memory.pressure", stall, threshold, window)
     }
 
 }
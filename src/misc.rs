@@ -0,0 +1,102 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    common::Max,
+    error::Result,
+    util::{
+        CGroupDir,
+        parse_flat_keyed_map,
+        read_flat_keyed_file_map,
+        write_single_value,
+    },
+};
+
+/// The misc controller, which limits scalar resources such as SEV ASIDs. All maps are keyed by
+/// resource name, e.g. `sev` or `sev_es`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Misc<F = Arc<Path>> {
+    dir: F
+}
+
+impl Misc {
+    pub fn new<P: Into<Arc<Path>>>(path: P) -> Self {
+        Misc::with_dir(path.into())
+    }
+}
+
+impl<F: CGroupDir> Misc<F> {
+    ///Open the interface files through `dir`, e.g. a [`CGroupFd`](crate::fd::CGroupFd).
+    pub fn with_dir(dir: F) -> Self {
+        Misc {
+            dir
+        }
+    }
+
+    ///How many units of each resource the host has. Only present on the root cgroup.
+    pub fn capacity(&self) -> Result<HashMap<String, u64>> {
+        let filename = "misc.capacity";
+        read_flat_keyed_file_map(&self.dir, filename)
+    }
+
+    pub fn max(&self) -> Result<HashMap<String, Max<u64>>> {
+        let filename = "misc.max";
+        read_flat_keyed_file_map(&self.dir, filename)
+    }
+
+    pub fn set_max(&self, resource: &str, max: Max<u64>) -> Result<()> {
+        let filename = "misc.max";
+        write_single_value(&self.dir, filename, format!("{} {}", resource, max))
+    }
+
+    pub fn current(&self) -> Result<HashMap<String, u64>> {
+        let filename = "misc.current";
+        read_flat_keyed_file_map(&self.dir, filename)
+    }
+
+    ///How often each resource was about to exceed misc.max in this cgroup and its descendants.
+    pub fn events(&self) -> Result<HashMap<String, u64>> {
+        let filename = "misc.events";
+        read_events(&self.dir, filename)
+    }
+
+    ///Like [`Misc::events`], but only counting this cgroup. Available since kernel 6.11.
+    pub fn events_local(&self) -> Result<HashMap<String, u64>> {
+        let filename = "misc.events.local";
+        read_events(&self.dir, filename)
+    }
+}
+
+fn read_events(parent: &impl CGroupDir, filename: &str) -> Result<HashMap<String, u64>> {
+    parse_events(&parent.read_file(filename)?)
+}
+
+///The kernel reports events as `<resource>.max <count>`; key them by resource name instead.
+fn parse_events(content: &str) -> Result<HashMap<String, u64>> {
+    let events: HashMap<String, u64> = parse_flat_keyed_map(content)?;
+    Ok(events
+        .into_iter()
+        .map(|(key, count)| match key.strip_suffix(".max") {
+            Some(resource) => (resource.to_string(), count),
+            None => (key, count),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::parse_events;
+
+    #[test]
+    fn events_by_resource() {
+        let events = parse_events("sev.max 3\nsev_es.max 0\n").unwrap();
+        let expected = HashMap::from([("sev".to_string(), 3), ("sev_es".to_string(), 0)]);
+        assert_eq!(events, expected);
+        assert!(parse_events("sev.max\n").is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+};
+
+use crate::{
+    error::{
+        CGroupError,
+        Result,
+    },
+    util::read_file_into_string,
+};
+
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+
+/// How cgroup hierarchies are laid out on this host.
+#[derive(Debug, Eq, PartialEq)]
+pub enum HierarchyMode {
+    /// Only cgroup2 is mounted, normally at /sys/fs/cgroup.
+    Unified,
+    /// cgroup v1 controllers plus a cgroup2 mount, normally at /sys/fs/cgroup/unified.
+    Hybrid,
+    /// Only cgroup v1 is mounted.
+    Legacy,
+}
+
+/// A single cgroup or cgroup2 line of a mountinfo file.
+#[derive(Debug, Eq, PartialEq)]
+pub struct CGroupMount {
+    ///Root of the mount within the filesystem
+    pub root: PathBuf,
+    pub mount_point: PathBuf,
+    ///"cgroup2" or "cgroup"
+    pub fs_type: String,
+    ///Per-mount options, e.g. `rw,nosuid`
+    pub mount_options: Vec<String>,
+    ///Per-superblock options, e.g. `rw,nsdelegate,memory_recursiveprot`
+    pub super_options: Vec<String>,
+}
+
+impl CGroupMount {
+    pub fn is_cgroup2(&self) -> bool {
+        self.fs_type == "cgroup2"
+    }
+
+    pub fn has_option(&self, option: &str) -> bool {
+        self.super_options.iter().any(|o| o == option)
+    }
+
+    pub fn nsdelegate(&self) -> bool {
+        self.has_option("nsdelegate")
+    }
+
+    pub fn memory_recursiveprot(&self) -> bool {
+        self.has_option("memory_recursiveprot")
+    }
+
+    pub fn favordynmods(&self) -> bool {
+        self.has_option("favordynmods")
+    }
+}
+
+/// Parse one mountinfo line. Returns `Ok(None)` for mounts that are not cgroups.
+///
+/// 36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue
+fn parse_mountinfo_line(line: &str) -> Result<Option<CGroupMount>> {
+    let err = || CGroupError::UnknownFieldErr(line.to_string());
+    let mut fields = line.split_whitespace();
+    let root = fields.nth(3).ok_or_else(err)?;
+    let mount_point = fields.next().ok_or_else(err)?;
+    let mount_options = fields.next().ok_or_else(err)?;
+    // optional fields are terminated by a single hyphen
+    fields.find(|f| *f == "-").ok_or_else(err)?;
+    let fs_type = fields.next().ok_or_else(err)?;
+    if fs_type != "cgroup2" && fs_type != "cgroup" {
+        return Ok(None);
+    }
+    let _source = fields.next().ok_or_else(err)?;
+    let super_options = fields.next().ok_or_else(err)?;
+    Ok(Some(CGroupMount {
+        root: PathBuf::from(unescape(root)),
+        mount_point: PathBuf::from(unescape(mount_point)),
+        fs_type: fs_type.to_string(),
+        mount_options: mount_options.split(',').map(String::from).collect(),
+        super_options: super_options.split(',').map(String::from).collect(),
+    }))
+}
+
+/// The kernel escapes space, tab, newline and backslash in paths as `\ooo` octal sequences.
+fn unescape(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            let oct = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("");
+            if let Ok(b) = u8::from_str_radix(oct, 8) {
+                out.push(b);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// All cgroup and cgroup2 mounts visible to this process.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Mounts {
+    pub mounts: Vec<CGroupMount>,
+}
+
+impl Mounts {
+    /// Read /proc/self/mountinfo
+    pub fn read() -> Result<Mounts> {
+        let content = read_file_into_string(Path::new(MOUNTINFO))?;
+        Mounts::from_str(&content)
+    }
+
+    pub fn cgroup2(&self) -> impl Iterator<Item = &CGroupMount> {
+        self.mounts.iter().filter(|m| m.is_cgroup2())
+    }
+
+    pub fn cgroup1(&self) -> impl Iterator<Item = &CGroupMount> {
+        self.mounts.iter().filter(|m| !m.is_cgroup2())
+    }
+
+    /// `None` if no cgroup filesystem is mounted at all.
+    pub fn mode(&self) -> Option<HierarchyMode> {
+        let v2 = self.cgroup2().next().is_some();
+        let v1 = self.cgroup1().next().is_some();
+        match (v2, v1) {
+            (true, false) => Some(HierarchyMode::Unified),
+            (true, true) => Some(HierarchyMode::Hybrid),
+            (false, true) => Some(HierarchyMode::Legacy),
+            (false, false) => None,
+        }
+    }
+
+    /// The cgroup2 mount this lib should operate on. A mount of the whole hierarchy is preferred
+    /// over bind mounts of a subtree.
+    pub fn unified(&self) -> Option<&CGroupMount> {
+        self.cgroup2()
+            .find(|m| m.root == Path::new("/"))
+            .or_else(|| self.cgroup2().next())
+    }
+}
+
+impl FromStr for Mounts {
+    type Err = CGroupError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut mounts = Vec::new();
+        for line in s.split('\n') {
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(mount) = parse_mountinfo_line(line)? {
+                mounts.push(mount);
+            }
+        }
+        Ok(Mounts {
+            mounts
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, str::FromStr};
+
+    use super::{HierarchyMode, Mounts};
+
+    const UNIFIED: &str = "\
+22 1 253:0 / / rw,relatime shared:1 - xfs /dev/mapper/root rw,attr2,inode64
+24 22 0:22 / /proc rw,nosuid,nodev,noexec,relatime shared:5 - proc proc rw
+26 25 0:23 / /sys/fs/cgroup rw,nosuid,nodev,noexec,relatime shared:4 - cgroup2 cgroup2 rw,nsdelegate,memory_recursiveprot
+";
+
+    const HYBRID: &str = "\
+25 21 0:22 / /sys/fs/cgroup ro,nosuid,nodev,noexec shared:9 - tmpfs tmpfs ro,mode=755
+26 25 0:23 / /sys/fs/cgroup/unified rw,nosuid,nodev,noexec,relatime shared:10 - cgroup2 cgroup2 rw,nsdelegate
+27 25 0:24 / /sys/fs/cgroup/systemd rw,nosuid,nodev,noexec,relatime shared:11 - cgroup cgroup rw,xattr,name=systemd
+30 25 0:27 / /sys/fs/cgroup/memory rw,nosuid,nodev,noexec,relatime shared:14 - cgroup cgroup rw,memory
+";
+
+    const LEGACY: &str = "\
+32 28 0:28 / /sys/fs/cgroup rw,relatime - tmpfs tmpfs rw,mode=755
+33 32 0:29 / /sys/fs/cgroup/cpu rw,relatime - cgroup cgroup rw,cpu
+";
+
+    #[test]
+    fn unified_host() {
+        let mounts = Mounts::from_str(UNIFIED).unwrap();
+        assert_eq!(mounts.mounts.len(), 1);
+        assert_eq!(mounts.mode(), Some(HierarchyMode::Unified));
+        let unified = mounts.unified().unwrap();
+        assert_eq!(unified.mount_point, PathBuf::from("/sys/fs/cgroup"));
+        assert!(unified.nsdelegate());
+        assert!(unified.memory_recursiveprot());
+        assert!(!unified.favordynmods());
+    }
+
+    #[test]
+    fn hybrid_host() {
+        let mounts = Mounts::from_str(HYBRID).unwrap();
+        assert_eq!(mounts.mode(), Some(HierarchyMode::Hybrid));
+        assert_eq!(mounts.cgroup1().count(), 2);
+        let unified = mounts.unified().unwrap();
+        assert_eq!(unified.mount_point, PathBuf::from("/sys/fs/cgroup/unified"));
+    }
+
+    #[test]
+    fn legacy_host() {
+        let mounts = Mounts::from_str(LEGACY).unwrap();
+        assert_eq!(mounts.mode(), Some(HierarchyMode::Legacy));
+        assert_eq!(mounts.unified(), None);
+        let mounts = Mounts::from_str("").unwrap();
+        assert_eq!(mounts.mode(), None);
+    }
+
+    #[test]
+    fn escaped_and_bind_mounts() {
+        let content = "\
+40 22 0:23 /user.slice /mnt/my\\040cgroup rw,relatime - cgroup2 cgroup2 rw,favordynmods
+41 22 0:23 / /sys/fs/cgroup rw,relatime - cgroup2 cgroup2 rw
+";
+        let mounts = Mounts::from_str(content).unwrap();
+        assert_eq!(mounts.mounts[0].mount_point, PathBuf::from("/mnt/my cgroup"));
+        assert_eq!(mounts.mounts[0].root, PathBuf::from("/user.slice"));
+        assert!(mounts.mounts[0].favordynmods());
+        assert_eq!(mounts.unified().unwrap().mount_point, PathBuf::from("/sys/fs/cgroup"));
+    }
+
+    #[test]
+    fn malformed_line() {
+        assert!(Mounts::from_str("40 22 0:23 /").is_err());
+        assert!(Mounts::from_str("40 22 0:23 / /mnt rw cgroup2 cgroup2 rw").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    common::Max,
+    error::Result,
+    util::{
+        CGroupDir,
+        read_flat_keyed_file,
+        read_single_value,
+        write_single_value,
+    },
+    FlatKeyedSetter,
+};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Pids<F = Arc<Path>> {
+    dir: F
+}
+
+impl Pids {
+    pub fn new<P: Into<Arc<Path>>>(path: P) -> Self {
+        Pids::with_dir(path.into())
+    }
+}
+
+impl<F: CGroupDir> Pids<F> {
+    ///Open the interface files through `dir`, e.g. a [`CGroupFd`](crate::fd::CGroupFd).
+    pub fn with_dir(dir: F) -> Self {
+        Pids {
+            dir
+        }
+    }
+
+    pub fn max(&self) -> Result<Max> {
+        let filename = "pids.max";
+        read_single_value(&self.dir, filename)
+    }
+
+    pub fn set_max(&self, max: Max) -> Result<()> {
+        let filename = "pids.max";
+        write_single_value(&self.dir, filename, max)
+    }
+
+    pub fn current(&self) -> Result<u64> {
+        let filename = "pids.current";
+        read_single_value(&self.dir, filename)
+    }
+
+    ///The highest value pids.current has reached. Available since kernel 6.1.
+    pub fn peak(&self) -> Result<u64> {
+        let filename = "pids.peak";
+        read_single_value(&self.dir, filename)
+    }
+
+    ///Fork failures in this cgroup and its descendants.
+    pub fn events(&self) -> Result<Event> {
+        let filename = "pids.events";
+        read_flat_keyed_file(&self.dir, filename)
+    }
+
+    ///Like [`Pids::events`], but only counting this cgroup. Available since kernel 6.13.
+    pub fn events_local(&self) -> Result<Event> {
+        let filename = "pids.events.local";
+        read_flat_keyed_file(&self.dir, filename)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Event {
+    ///Forks that failed because of pids.max, counted as described for [`Pids::events`] and
+    ///[`Pids::events_local`]
+    pub max: u64,
+}
+
+impl FlatKeyedSetter<u64> for Event {
+    fn new() -> Self {
+        Event {
+            max: 0
+        }
+    }
+
+    fn set(&mut self, s: &str, val: u64) {
+        if s == "max" {
+            self.max = val;
+        }
+    }
+}
@@ -1,22 +1,62 @@
-use std::str::FromStr;
-use crate::error::{
-    Result,
-    CGroupError
+use std::{
+    fmt,
+    fs::{
+        File,
+        OpenOptions,
+    },
+    io::{
+        ErrorKind,
+        Write,
+    },
+    os::unix::{
+        fs::OpenOptionsExt,
+        io::{
+            AsRawFd,
+            RawFd,
+        },
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+    time::{
+        Duration,
+        Instant,
+    },
 };
+use crate::{
+    error::{
+        Result,
+        CGroupError
+    },
+    util::{
+        poll_timeout_ms,
+        read_value,
+        CGroupDir,
+    },
+};
+
+/// Where the kernel exposes the pressure of the whole system.
+pub const SYSTEM_PRESSURE_ROOT: &str = "/proc/pressure";
 
 #[derive(Debug, PartialEq)]
 pub struct CPUPressure {
-    pub(crate) some: PSIMetric,
+    pub some: PSIMetric,
+    ///Reported by kernels 5.13 and newer
+    pub full: Option<PSIMetric>,
 }
 
 impl FromStr for CPUPressure {
     type Err = CGroupError;
 
     //some avg10=0.00 avg60=0.00 avg300=0.00 total=0
+    //full avg10=0.00 avg60=0.00 avg300=0.00 total=0
     fn from_str(s: &str) -> Result<Self> {
-        let metric = PSIMetric::from_str(s)?;
-        return Ok(CPUPressure {
-            some: metric
+        let (some, full) = parse_pressure(s)?;
+        Ok(CPUPressure {
+            some: some.ok_or(CGroupError::UnknownFieldErr(s.to_string()))?,
+            full,
         })
     }
 }
@@ -31,35 +71,86 @@ impl FromStr for MemoryPressure {
     type Err = CGroupError;
 
     fn from_str(s: &str) -> Result<Self> {
-        let mut lines = s.split('\n');
-        let mut pressure = MemoryPressure {
-            some: PSIMetric {
-                key: "".to_string(),
-                avg10: 0.0,
-                avg60: 0.0,
-                avg300: 0.0,
-                total: 0
-            },
-            full: PSIMetric {
-                key: "".to_string(),
-                avg10: 0.0,
-                avg60: 0.0,
-                avg300: 0.0,
-                total: 0
-            }
-        };
-        while let Some(next) = lines.next() {
-            if !next.is_empty() {
-                let metric = PSIMetric::from_str(next)?;
-                match metric.key.as_ref() {
-                    "some" => pressure.some = metric,
-                    "full" => pressure.full = metric,
-                    _ => {}
-                }
+        let (some, full) = parse_pressure(s)?;
+        Ok(MemoryPressure {
+            some: some.ok_or(CGroupError::UnknownFieldErr(s.to_string()))?,
+            full: full.ok_or(CGroupError::UnknownFieldErr(s.to_string()))?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IOPressure {
+    pub some: PSIMetric,
+    pub full: PSIMetric
+}
+
+impl FromStr for IOPressure {
+    type Err = CGroupError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (some, full) = parse_pressure(s)?;
+        Ok(IOPressure {
+            some: some.ok_or(CGroupError::UnknownFieldErr(s.to_string()))?,
+            full: full.ok_or(CGroupError::UnknownFieldErr(s.to_string()))?,
+        })
+    }
+}
+
+///irq.pressure, available with CONFIG_IRQ_TIME_ACCOUNTING. IRQ time is always a full stall, so
+///there is no `some` line.
+#[derive(Debug, PartialEq)]
+pub struct IRQPressure {
+    pub full: PSIMetric
+}
+
+impl FromStr for IRQPressure {
+    type Err = CGroupError;
+
+    //full avg10=0.00 avg60=0.00 avg300=0.00 total=0
+    fn from_str(s: &str) -> Result<Self> {
+        let (_, full) = parse_pressure(s)?;
+        Ok(IRQPressure {
+            full: full.ok_or(CGroupError::UnknownFieldErr(s.to_string()))?,
+        })
+    }
+}
+
+///Split a pressure file into its `some` and `full` lines.
+fn parse_pressure(s: &str) -> Result<(Option<PSIMetric>, Option<PSIMetric>)> {
+    let mut some = None;
+    let mut full = None;
+    for next in s.split('\n') {
+        if !next.is_empty() {
+            let metric = PSIMetric::from_str(next)?;
+            match metric.key.as_ref() {
+                "some" => some = Some(metric),
+                "full" => full = Some(metric),
+                _ => {}
             }
         }
-        Ok(pressure)
     }
+    Ok((some, full))
+}
+
+///`/proc/pressure/cpu`
+pub fn system_cpu_pressure() -> Result<CPUPressure> {
+    read_value(Path::new(SYSTEM_PRESSURE_ROOT), "cpu")
+}
+
+///`/proc/pressure/memory`
+pub fn system_memory_pressure() -> Result<MemoryPressure> {
+    read_value(Path::new(SYSTEM_PRESSURE_ROOT), "memory")
+}
+
+///`/proc/pressure/io`
+pub fn system_io_pressure() -> Result<IOPressure> {
+    read_value(Path::new(SYSTEM_PRESSURE_ROOT), "io")
+}
+
+///`/proc/pressure/irq`
+pub fn system_irq_pressure() -> Result<IRQPressure> {
+    read_value(Path::new(SYSTEM_PRESSURE_ROOT), "irq")
 }
 
 #[derive(Debug, PartialEq)]
@@ -72,6 +163,15 @@ pub struct PSIMetric {
 }
 
 impl PSIMetric {
+    fn empty(key: &str) -> Self {
+        PSIMetric {
+            key: key.to_string(),
+            avg10: 0.0,
+            avg60: 0.0,
+            avg300: 0.0,
+            total: 0
+        }
+    }
 
     pub fn set(&mut self, key: &str, val: &str) -> Result<()>{
         match key {
@@ -97,7 +197,7 @@ impl PSIMetric {
             },
             _ => {}
         }
-        return Ok(())
+        Ok(())
     }
 }
 
@@ -108,14 +208,8 @@ impl FromStr for PSIMetric {
         let mut splits = s.split_whitespace();
         let key = splits.next()
             .ok_or(CGroupError::UnknownFieldErr(s.to_string()))?;
-        let mut metric = PSIMetric {
-            key: key.to_string(),
-            avg10: 0.0,
-            avg60: 0.0,
-            avg300: 0.0,
-            total: 0
-        };
-        while let Some(next) = splits.next() {
+        let mut metric = PSIMetric::empty(key);
+        for next in splits {
             let mut kv = next.split('=');
             let key = kv.next().ok_or(CGroupError::UnknownFieldErr(String::from(s)))?;
             let val = kv.next().ok_or(CGroupError::UnknownFieldErr(String::from(s)))?;
@@ -123,4 +217,150 @@ impl FromStr for PSIMetric {
         }
         Ok(metric)
     }
-}
\ No newline at end of file
+}
+
+///Which share of the tasks has to be stalled for a [`PressureTrigger`] to count the time.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Stall {
+    ///At least one task is stalled
+    Some,
+    ///All non-idle tasks are stalled at the same time
+    Full,
+}
+
+impl fmt::Display for Stall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stall::Some => write!(f, "some"),
+            Stall::Full => write!(f, "full"),
+        }
+    }
+}
+
+/// A PSI trigger registered on a `*.pressure` file.
+///
+/// The trigger fires when the tasks were stalled for more than `threshold` within any `window`
+/// of time. It stays registered for as long as the value is alive. Besides blocking in
+/// [`PressureTrigger::wait`], its file descriptor can be registered with poll/epoll for `POLLPRI`.
+///
+/// The kernel accepts windows between 500ms and 10s. Unprivileged users can only use windows
+/// that are a multiple of 2s.
+#[derive(Debug)]
+pub struct PressureTrigger {
+    file: File,
+    path: PathBuf,
+}
+
+impl PressureTrigger {
+    ///Register a trigger on the pressure file at `path`, e.g. `/proc/pressure/memory` or the
+    ///`memory.pressure` file of a cgroup.
+    pub fn new<P: AsRef<Path>>(path: P, stall: Stall, threshold: Duration, window: Duration) -> Result<PressureTrigger> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+            .map_err(|e| CGroupError::FSErr(e.kind()))?;
+        Self::register(file, PathBuf::from(path), stall, threshold, window)
+    }
+
+    ///Register a trigger on the pressure file `filename` of a cgroup directory.
+    pub(crate) fn open(dir: &impl CGroupDir, filename: &str, stall: Stall, threshold: Duration, window: Duration) -> Result<PressureTrigger> {
+        let file = dir.open_file(filename, libc::O_RDWR | libc::O_NONBLOCK)?;
+        Self::register(file, dir.path().join(filename), stall, threshold, window)
+    }
+
+    fn register(mut file: File, path: PathBuf, stall: Stall, threshold: Duration, window: Duration) -> Result<PressureTrigger> {
+        // the kernel overwrites the last byte of the buffer with a NUL, so terminate it ourselves
+        let trigger = format!("{} {} {}\0", stall, threshold.as_micros(), window.as_micros());
+        match file.write(trigger.as_bytes()) {
+            Ok(0) => return Err(CGroupError::WriteZeroByteErr),
+            Ok(_) => {}
+            Err(err) => return Err(CGroupError::FSErr(err.kind())),
+        }
+        Ok(PressureTrigger {
+            file,
+            path,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    ///Block until the trigger fires. Returns `false` if `timeout` expired first; `None` waits
+    ///forever. Fails with `NotFound` once the cgroup the trigger was registered on is removed.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<bool> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            let mut pfd = libc::pollfd {
+                fd: self.file.as_raw_fd(),
+                events: libc::POLLPRI,
+                revents: 0,
+            };
+            match unsafe { libc::poll(&mut pfd, 1, poll_timeout_ms(deadline)) } {
+                n if n < 0 => {
+                    let err = std::io::Error::last_os_error();
+                    if err.kind() != ErrorKind::Interrupted {
+                        return Err(CGroupError::FSErr(err.kind()));
+                    }
+                }
+                0 if deadline.is_some_and(|d| Instant::now() >= d) => return Ok(false),
+                0 => {}
+                _ if pfd.revents & libc::POLLERR != 0 => return Err(CGroupError::FSErr(ErrorKind::NotFound)),
+                _ if pfd.revents & libc::POLLPRI != 0 => return Ok(true),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl AsRawFd for PressureTrigger {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, str::FromStr, time::Duration};
+
+    use super::{CPUPressure, IRQPressure, IOPressure, MemoryPressure, PressureTrigger, Stall};
+    use crate::error::CGroupError;
+
+    #[test]
+    fn parse_pressure() {
+        let old = CPUPressure::from_str("some avg10=1.50 avg60=0.00 avg300=0.00 total=42\n").unwrap();
+        assert_eq!(old.some.avg10, 1.5);
+        assert_eq!(old.some.total, 42);
+        assert_eq!(old.full, None);
+
+        let content = "some avg10=0.00 avg60=0.00 avg300=0.00 total=3\nfull avg10=0.00 avg60=0.00 avg300=0.00 total=2\n";
+        assert_eq!(CPUPressure::from_str(content).unwrap().full.map(|m| m.total), Some(2));
+        let io = IOPressure::from_str(content).unwrap();
+        assert_eq!((io.some.total, io.full.total), (3, 2));
+        assert_eq!(MemoryPressure::from_str(content).unwrap().full.total, 2);
+
+        let truncated = "some avg10=0.00 avg60=0.00 avg300=0.00 total=3\n";
+        assert_eq!(IOPressure::from_str(truncated), Err(CGroupError::UnknownFieldErr(truncated.to_string())));
+        assert_eq!(MemoryPressure::from_str(truncated), Err(CGroupError::UnknownFieldErr(truncated.to_string())));
+        assert_eq!(IOPressure::from_str(""), Err(CGroupError::UnknownFieldErr(String::new())));
+        assert_eq!(MemoryPressure::from_str(""), Err(CGroupError::UnknownFieldErr(String::new())));
+
+        let irq = IRQPressure::from_str("full avg10=0.00 avg60=0.00 avg300=0.00 total=7\n").unwrap();
+        assert_eq!(irq.full.total, 7);
+        assert!(IRQPressure::from_str("").is_err());
+    }
+
+    #[test]
+    fn register_trigger() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memory.pressure");
+        fs::write(&path, "").unwrap();
+        let trigger = PressureTrigger::new(&path, Stall::Some, Duration::from_millis(150), Duration::from_secs(1)).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"some 150000 1000000\0");
+        // a regular file never reports POLLPRI, so this can only time out
+        assert_eq!(trigger.wait(Some(Duration::from_millis(10))), Ok(false));
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{
+    common::Max,
+    error::{
+        CGroupError,
+        Result,
+    },
+    util::{
+        CGroupDir,
+        read_nested_keyed_file,
+        write_single_value,
+    },
+};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rdma<F = Arc<Path>> {
+    dir: F
+}
+
+impl Rdma {
+    pub fn new<P: Into<Arc<Path>>>(path: P) -> Self {
+        Rdma::with_dir(path.into())
+    }
+}
+
+impl<F: CGroupDir> Rdma<F> {
+    ///Open the interface files through `dir`, e.g. a [`CGroupFd`](crate::fd::CGroupFd).
+    pub fn with_dir(dir: F) -> Self {
+        Rdma {
+            dir
+        }
+    }
+
+    ///Limits per RDMA device, keyed by device name
+    pub fn max(&self) -> Result<HashMap<String, RdmaMax>> {
+        let filename = "rdma.max";
+        read_nested_keyed_file(&self.dir, filename)
+    }
+
+    pub fn set_max(&self, device: &str, max: &RdmaMax) -> Result<()> {
+        let filename = "rdma.max";
+        write_single_value(&self.dir, filename, format!("{} {}", device, max))
+    }
+
+    ///Usage per RDMA device, keyed by device name
+    pub fn current(&self) -> Result<HashMap<String, RdmaCurrent>> {
+        let filename = "rdma.current";
+        read_nested_keyed_file(&self.dir, filename)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RdmaMax {
+    pub hca_handle: Max,
+    pub hca_object: Max,
+}
+
+impl FromStr for RdmaMax {
+    type Err = CGroupError;
+
+    //hca_handle=2 hca_object=max
+    fn from_str(s: &str) -> Result<Self> {
+        let mut max = RdmaMax {
+            hca_handle: Max::Max,
+            hca_object: Max::Max,
+        };
+        for next in s.split_whitespace() {
+            let (key, val) = next.split_once('=')
+                .ok_or(CGroupError::UnknownFieldErr(s.to_string()))?;
+            match key {
+                "hca_handle" => max.hca_handle = Max::from_str(val)?,
+                "hca_object" => max.hca_object = Max::from_str(val)?,
+                _ => {}
+            }
+        }
+        Ok(max)
+    }
+}
+
+impl fmt::Display for RdmaMax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "hca_handle={} hca_object={}", self.hca_handle, self.hca_object)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RdmaCurrent {
+    pub hca_handle: u32,
+    pub hca_object: u32,
+}
+
+impl FromStr for RdmaCurrent {
+    type Err = CGroupError;
+
+    //hca_handle=1 hca_object=20
+    fn from_str(s: &str) -> Result<Self> {
+        let mut current = RdmaCurrent {
+            hca_handle: 0,
+            hca_object: 0,
+        };
+        for next in s.split_whitespace() {
+            let (key, val) = next.split_once('=')
+                .ok_or(CGroupError::UnknownFieldErr(s.to_string()))?;
+            let val = u32::from_str(val)
+                .map_err(|_| CGroupError::UnknownFieldErr(s.to_string()))?;
+            match key {
+                "hca_handle" => current.hca_handle = val,
+                "hca_object" => current.hca_object = val,
+                _ => {}
+            }
+        }
+        Ok(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{RdmaCurrent, RdmaMax};
+    use crate::{common::Max, util::parse_nested_keyed};
+
+    #[test]
+    fn parse_rdma() {
+        let max: HashMap<String, RdmaMax> =
+            parse_nested_keyed("mlx4_0 hca_handle=2 hca_object=2000\nocrdma1 hca_handle=3 hca_object=max\n").unwrap();
+        assert_eq!(max["mlx4_0"], RdmaMax { hca_handle: Max::Val(2), hca_object: Max::Val(2000) });
+        assert_eq!(max["ocrdma1"].hca_object, Max::Max);
+        assert_eq!(max["ocrdma1"].to_string(), "hca_handle=3 hca_object=max");
+
+        let current: HashMap<String, RdmaCurrent> =
+            parse_nested_keyed("mlx4_0 hca_handle=1 hca_object=20\n").unwrap();
+        assert_eq!(current["mlx4_0"], RdmaCurrent { hca_handle: 1, hca_object: 20 });
+    }
+}
@@ -1,7 +1,12 @@
 use std::{
-    fs,
-    io::Read,
+    ffi::CString,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    mem::MaybeUninit,
+    os::unix::{ffi::OsStrExt, fs::OpenOptionsExt},
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
 };
 use std::{hash::Hash, str::FromStr};
 
@@ -9,6 +14,96 @@ use crate::error::{CGroupError, Result};
 use crate::FlatKeyedSetter;
 use std::collections::HashMap;
 
+/// Where interface files are opened: a cgroup directory given by path, or a
+/// [`CGroupFd`](crate::fd::CGroupFd), which opens them relative to its directory fd. The
+/// controller views and the readers below work with either.
+pub trait CGroupDir {
+    /// The directory, or the path it was opened from.
+    fn path(&self) -> &Path;
+
+    /// Open `filename` in the directory with the given open(2) flags.
+    fn open_file(&self, filename: &str, flags: libc::c_int) -> Result<File>;
+
+    /// Read a whole interface file.
+    fn read_file(&self, filename: &str) -> Result<String> {
+        let mut file = self.open_file(filename, libc::O_RDONLY)?;
+        let mut buf = String::new();
+        file.read_to_string(&mut buf)
+            .map_err(|e| CGroupError::FSErr(e.kind()))?;
+        Ok(buf)
+    }
+
+    /// Write `content` to an interface file with a single write call.
+    fn write_file(&self, filename: &str, content: &[u8]) -> Result<()> {
+        let mut file = self.open_file(filename, libc::O_WRONLY)?;
+        match file.write(content) {
+            Ok(0) => Err(CGroupError::WriteZeroByteErr),
+            Ok(_) => Ok(()),
+            Err(err) => Err(CGroupError::FSErr(err.kind())),
+        }
+    }
+}
+
+impl CGroupDir for Path {
+    fn path(&self) -> &Path {
+        self
+    }
+
+    fn open_file(&self, filename: &str, flags: libc::c_int) -> Result<File> {
+        let mut path = PathBuf::from(self);
+        path.push(filename);
+        let access = flags & libc::O_ACCMODE;
+        OpenOptions::new()
+            .read(access != libc::O_WRONLY)
+            .write(access != libc::O_RDONLY)
+            .custom_flags(flags)
+            .open(path)
+            .map_err(|e| CGroupError::FSErr(e.kind()))
+    }
+}
+
+impl<D: CGroupDir + ?Sized> CGroupDir for &D {
+    fn path(&self) -> &Path {
+        (**self).path()
+    }
+
+    fn open_file(&self, filename: &str, flags: libc::c_int) -> Result<File> {
+        (**self).open_file(filename, flags)
+    }
+}
+
+impl<D: CGroupDir + ?Sized> CGroupDir for Arc<D> {
+    fn path(&self) -> &Path {
+        (**self).path()
+    }
+
+    fn open_file(&self, filename: &str, flags: libc::c_int) -> Result<File> {
+        (**self).open_file(filename, flags)
+    }
+}
+
+/// The error of the last failed libc call.
+pub(crate) fn last_os_error() -> CGroupError {
+    CGroupError::FSErr(std::io::Error::last_os_error().kind())
+}
+
+/// Fails with `InvalidInput` if `s` contains a NUL byte.
+pub(crate) fn c_string(s: &[u8]) -> Result<CString> {
+    CString::new(s).map_err(|_| CGroupError::FSErr(std::io::ErrorKind::InvalidInput))
+}
+
+/// The poll(2) timeout that waits until `deadline`, or forever for `None`.
+pub(crate) fn poll_timeout_ms(deadline: Option<Instant>) -> libc::c_int {
+    match deadline {
+        Some(deadline) => {
+            let left = deadline.saturating_duration_since(Instant::now());
+            // round up so that we never wake up just before the deadline
+            (left.as_millis() + 1).min(libc::c_int::MAX as u128) as libc::c_int
+        }
+        None => -1,
+    }
+}
+
 pub fn read_file_into_string(path: &Path) -> Result<String> {
     match std::fs::File::open(path) {
         Ok(mut file) => {
@@ -22,10 +117,22 @@ pub fn read_file_into_string(path: &Path) -> Result<String> {
     }
 }
 
+/// Check whether `path` is a directory that lives on a cgroup2 filesystem.
+pub fn is_cgroup2_dir(path: &Path) -> Result<bool> {
+    let c_path = c_string(path.as_os_str().as_bytes())?;
+    let mut buf = MaybeUninit::<libc::statfs>::uninit();
+    let ret = unsafe { libc::statfs(c_path.as_ptr(), buf.as_mut_ptr()) };
+    if ret != 0 {
+        return Err(last_os_error());
+    }
+    let buf = unsafe { buf.assume_init() };
+    // the field and the constant differ in signedness on some targets, e.g. musl
+    Ok(buf.f_type as u64 == libc::CGROUP2_SUPER_MAGIC as u64 && path.is_dir())
+}
+
 pub fn read_space_separated_values<T: FromStr>(content: String) -> Vec<T> {
     content
         .split_whitespace()
-        .into_iter()
         .map(|s| T::from_str(s))
         .filter_map(std::result::Result::ok)
         .collect()
@@ -39,10 +146,12 @@ pub fn read_newline_separated_values<T: FromStr>(content: String) -> Vec<T> {
         .collect()
 }
 
-pub fn read_single_value<T: FromStr>(parent: &Path, filename: &str) -> Result<T> {
-    let mut path = PathBuf::from(parent);
-    path.push(filename);
-    let content = read_file_into_string(&path)?;
+pub fn read_single_value<T: FromStr>(parent: &(impl CGroupDir + ?Sized), filename: &str) -> Result<T> {
+    let content = parent.read_file(filename)?;
+    parse_single_value(content)
+}
+
+pub fn parse_single_value<T: FromStr>(content: String) -> Result<T> {
     if let Some(w) = content.split('\n').next() {
         let w = T::from_str(w).map_err(|_| CGroupError::UnknownFieldErr(content))?;
         return Ok(w);
@@ -50,30 +159,31 @@ pub fn read_single_value<T: FromStr>(parent: &Path, filename: &str) -> Result<T>
     Err(CGroupError::UnknownFieldErr(content))
 }
 
-pub fn read_value<T: FromStr>(parent: &Path, filename: &str) -> Result<T> {
-    let mut path = PathBuf::from(parent);
-    path.push(filename);
-    let content = read_file_into_string(&path)?;
-    Ok(T::from_str(content.as_str()).map_err(|_| CGroupError::UnknownFieldErr(content))?)
+pub fn read_value<T: FromStr>(parent: &(impl CGroupDir + ?Sized), filename: &str) -> Result<T> {
+    let content = parent.read_file(filename)?;
+    T::from_str(content.as_str()).map_err(|_| CGroupError::UnknownFieldErr(content))
 }
 
-pub fn write_single_value<T: ToString>(parent: &Path, filename: &str, t: T) -> Result<()> {
-    let mut path = PathBuf::from(parent);
-    path.push(filename);
-    fs::write(path.as_path(), t.to_string()).map_err(|e| CGroupError::FSErr(e.kind()))
+pub fn write_single_value<T: ToString>(parent: &(impl CGroupDir + ?Sized), filename: &str, t: T) -> Result<()> {
+    parent.write_file(filename, t.to_string().as_bytes())
 }
 
-pub fn read_flat_keyed_file<V, T>(parent: &Path, filename: &str) -> Result<T>
+pub fn read_flat_keyed_file<V, T>(parent: &(impl CGroupDir + ?Sized), filename: &str) -> Result<T>
+where
+    V: FromStr,
+    T: FlatKeyedSetter<V>,
+{
+    let content = parent.read_file(filename)?;
+    parse_flat_keyed(&content)
+}
+
+pub fn parse_flat_keyed<V, T>(content: &str) -> Result<T>
 where
     V: FromStr,
     T: FlatKeyedSetter<V>,
 {
-    let mut path = PathBuf::from(parent);
-    path.push(filename);
-    let content = read_file_into_string(&path)?;
     let mut t = T::new();
-    let mut splits = content.split('\n');
-    while let Some(line) = splits.next() {
+    for line in content.split('\n') {
         if line.is_empty() {
             break;
         }
@@ -91,16 +201,20 @@ where
     Ok(t)
 }
 
-pub fn read_flat_keyed_file_map<V>(parent: &Path, filename: &str) -> Result<HashMap<String, V>>
+pub fn read_flat_keyed_file_map<V>(parent: &(impl CGroupDir + ?Sized), filename: &str) -> Result<HashMap<String, V>>
+where
+    V: FromStr,
+{
+    let content = parent.read_file(filename)?;
+    parse_flat_keyed_map(&content)
+}
+
+pub fn parse_flat_keyed_map<V>(content: &str) -> Result<HashMap<String, V>>
 where
     V: FromStr,
 {
-    let mut path = PathBuf::from(parent);
-    path.push(filename);
-    let content = read_file_into_string(&path)?;
     let mut map = HashMap::new();
-    let mut splits = content.split('\n');
-    while let Some(line) = splits.next() {
+    for line in content.split('\n') {
         if line.is_empty() {
             break;
         }
@@ -119,7 +233,7 @@ where
 }
 
 pub fn read_nested_keyed_file_to_map<K1, K2, V2>(
-    parent: &Path,
+    parent: &(impl CGroupDir + ?Sized),
     filename: &str,
 ) -> Result<HashMap<K1, HashMap<K2, V2>>>
 where
@@ -127,12 +241,18 @@ where
     K2: FromStr + Eq + Hash,
     V2: FromStr,
 {
-    let mut path = PathBuf::from(parent);
-    path.push(filename);
-    let content = read_file_into_string(&path)?;
+    let content = parent.read_file(filename)?;
+    parse_nested_keyed_to_map(&content)
+}
+
+pub fn parse_nested_keyed_to_map<K1, K2, V2>(content: &str) -> Result<HashMap<K1, HashMap<K2, V2>>>
+where
+    K1: FromStr + Eq + Hash,
+    K2: FromStr + Eq + Hash,
+    V2: FromStr,
+{
     let mut map = HashMap::new();
-    let mut lines = content.split('\n');
-    while let Some(line) = lines.next() {
+    for line in content.split('\n') {
         if line.is_empty() {
             break;
         }
@@ -141,7 +261,7 @@ where
             let k1 = K1::from_str(first)
                 .map_err(|_| CGroupError::UnknownFieldErr(content.to_string()))?;
             let mut nest_map = HashMap::new();
-            while let Some(next) = splits.next() {
+            for next in splits {
                 let mut kv_split = next.split('=');
                 let k = kv_split
                     .next()
@@ -162,24 +282,29 @@ where
     Ok(map)
 }
 
-pub fn read_nested_keyed_file<K, V>(parent: &Path, filename: &str) -> Result<HashMap<K, V>>
+pub fn read_nested_keyed_file<K, V>(parent: &(impl CGroupDir + ?Sized), filename: &str) -> Result<HashMap<K, V>>
+where
+    K: FromStr + Eq + Hash,
+    V: FromStr<Err=CGroupError>,
+{
+    let content = parent.read_file(filename)?;
+    parse_nested_keyed(&content)
+}
+
+pub fn parse_nested_keyed<K, V>(content: &str) -> Result<HashMap<K, V>>
 where
-    K: FromStr<Err=CGroupError> + Eq + Hash,
+    K: FromStr + Eq + Hash,
     V: FromStr<Err=CGroupError>,
 {
-    let mut path = PathBuf::from(parent);
-    path.push(filename);
-    let content = read_file_into_string(&path)?;
     let mut map = HashMap::new();
-    let mut lines = content.split('\n');
-    while let Some(line) = lines.next() {
+    for line in content.split('\n') {
         if line.is_empty() {
             break;
         }
         let mut kv = line.splitn(2, ' ');
         let k = kv.next().ok_or(CGroupError::UnknownFieldErr(content.to_string()))?;
         let v = kv.next().ok_or(CGroupError::UnknownFieldErr(content.to_string()))?;
-        let k = K::from_str(k)?;
+        let k = K::from_str(k).map_err(|_| CGroupError::UnknownFieldErr(k.to_string()))?;
         let v = V::from_str(v)?;
         map.insert(k, v);
     }
@@ -0,0 +1,217 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use crate::{
+    cgroup::CGroup,
+    error::{
+        CGroupError,
+        Result,
+    },
+};
+
+/// A cgroup found while walking a hierarchy.
+#[derive(Debug, Eq, PartialEq)]
+pub struct WalkEntry {
+    pub path: PathBuf,
+    ///1 for direct children of the cgroup the walk started from
+    pub depth: usize,
+}
+
+impl WalkEntry {
+    pub fn cgroup(&self) -> CGroup {
+        CGroup::new(self.path.as_path())
+    }
+}
+
+/// List the child cgroups of `path`, sorted by name. Interface files such as `cgroup.procs` are
+/// skipped, and so are children that disappear while the directory is being read.
+pub fn children(path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = fs::read_dir(path).map_err(|e| CGroupError::FSErr(e.kind()))?;
+    let mut children = Vec::new();
+    for entry in dir {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(CGroupError::FSErr(e.kind())),
+        };
+        match entry.file_type() {
+            Ok(t) if t.is_dir() => children.push(entry.path()),
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(CGroupError::FSErr(e.kind())),
+        }
+    }
+    children.sort();
+    Ok(children)
+}
+
+type Prune<'f> = Box<dyn FnMut(&WalkEntry) -> bool + 'f>;
+
+/// Depth-first, pre-order iterator over all cgroups below a root.
+///
+/// Cgroups removed while the walk is in progress are skipped instead of being reported as errors.
+pub struct Descendants<'f> {
+    root: Option<PathBuf>,
+    stack: Vec<WalkEntry>,
+    max_depth: Option<usize>,
+    prune: Option<Prune<'f>>,
+    pending: Option<CGroupError>,
+}
+
+impl<'f> Descendants<'f> {
+    pub fn new(root: &Path) -> Self {
+        Descendants {
+            root: Some(PathBuf::from(root)),
+            stack: Vec::new(),
+            max_depth: None,
+            prune: None,
+            pending: None,
+        }
+    }
+
+    /// Do not yield cgroups nested deeper than `depth` below the root.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Skip every entry for which `prune` returns true, together with all of its descendants.
+    pub fn prune<F>(mut self, prune: F) -> Self
+    where
+        F: FnMut(&WalkEntry) -> bool + 'f,
+    {
+        self.prune = Some(Box::new(prune));
+        self
+    }
+
+    fn descend(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max| depth < max)
+    }
+
+    fn push_children(&mut self, mut children: Vec<PathBuf>, depth: usize) {
+        // reversed, so that popping from the stack yields children in name order
+        while let Some(path) = children.pop() {
+            self.stack.push(WalkEntry {
+                path,
+                depth,
+            });
+        }
+    }
+}
+
+impl<'f> Iterator for Descendants<'f> {
+    type Item = Result<WalkEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.pending.take() {
+            return Some(Err(err));
+        }
+        if let Some(root) = self.root.take() {
+            if self.descend(0) {
+                match children(&root) {
+                    Ok(children) => self.push_children(children, 1),
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+        }
+        loop {
+            let entry = self.stack.pop()?;
+            if let Some(prune) = self.prune.as_mut() {
+                if prune(&entry) {
+                    continue;
+                }
+            }
+            if self.descend(entry.depth) {
+                match children(&entry.path) {
+                    Ok(children) => self.push_children(children, entry.depth + 1),
+                    Err(CGroupError::FSErr(ErrorKind::NotFound)) => continue,
+                    Err(err) => self.pending = Some(err),
+                }
+            }
+            return Some(Ok(entry));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use tempfile::TempDir;
+
+    use super::{children, Descendants};
+    use crate::error::CGroupError;
+
+    fn tree() -> TempDir {
+        let root = tempfile::tempdir().unwrap();
+        for dir in &["a/x/deep", "a/y", "b", "c"] {
+            fs::create_dir_all(root.path().join(dir)).unwrap();
+        }
+        fs::write(root.path().join("cgroup.procs"), "").unwrap();
+        fs::write(root.path().join("a/cgroup.events"), "").unwrap();
+        root
+    }
+
+    fn relative(root: &Path, entries: Descendants) -> Vec<(String, usize)> {
+        entries
+            .map(|e| e.unwrap())
+            .map(|e| (e.path.strip_prefix(root).unwrap().display().to_string(), e.depth))
+            .collect()
+    }
+
+    #[test]
+    fn list_children() {
+        let dir = tree();
+        let root = dir.path();
+        let result = children(root).unwrap();
+        assert_eq!(result, vec![root.join("a"), root.join("b"), root.join("c")]);
+    }
+
+    #[test]
+    fn walk_depth_first() {
+        let dir = tree();
+        let root = dir.path();
+        let result = relative(root, Descendants::new(root));
+        let expect = vec![("a", 1), ("a/x", 2), ("a/x/deep", 3), ("a/y", 2), ("b", 1), ("c", 1)];
+        let expect: Vec<(String, usize)> = expect.into_iter().map(|(p, d)| (p.to_string(), d)).collect();
+        assert_eq!(result, expect);
+    }
+
+    #[test]
+    fn walk_max_depth_and_prune() {
+        let dir = tree();
+        let root = dir.path();
+        let result = relative(root, Descendants::new(root).max_depth(1));
+        assert_eq!(result.len(), 3);
+        let result = relative(root, Descendants::new(root).max_depth(0));
+        assert!(result.is_empty());
+        let result = relative(root, Descendants::new(root).prune(|e| e.path.ends_with("x")));
+        assert_eq!(result.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>(), vec!["a", "a/y", "b", "c"]);
+    }
+
+    #[test]
+    fn walk_tolerates_vanished_children() {
+        let dir = tree();
+        let root = dir.path();
+        let b = root.join("b");
+        let walk = Descendants::new(root).prune(|e| {
+            if e.path.ends_with("a") {
+                fs::remove_dir(&b).unwrap();
+            }
+            false
+        });
+        let result = relative(root, walk);
+        assert_eq!(result.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>(), vec!["a", "a/x", "a/x/deep", "a/y", "c"]);
+        fs::remove_dir_all(root).unwrap();
+
+        let mut walk = Descendants::new(root);
+        assert_eq!(walk.next().unwrap(), Err(CGroupError::FSErr(std::io::ErrorKind::NotFound)));
+        assert!(walk.next().is_none());
+    }
+}
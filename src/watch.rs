@@ -0,0 +1,260 @@
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    os::unix::{
+        ffi::OsStrExt,
+        io::{
+            AsRawFd,
+            FromRawFd,
+            OwnedFd,
+            RawFd,
+        },
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use crate::{
+    cgroup::{
+        CGroup,
+        CGroupEvent,
+    },
+    error::{
+        CGroupError,
+        Result,
+    },
+    util::{
+        c_string,
+        last_os_error,
+        poll_timeout_ms,
+    },
+};
+
+const EVENT_HEADER_SIZE: usize = std::mem::size_of::<libc::inotify_event>();
+
+/// A change of cgroup.events observed by an [`EventWatcher`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EventTransition {
+    pub c_group: CGroup,
+    pub old: CGroupEvent,
+    ///`None` once the cgroup has been removed
+    pub new: Option<CGroupEvent>,
+}
+
+struct Watched {
+    c_group: CGroup,
+    last: CGroupEvent,
+}
+
+/// Watches cgroup.events of any number of cgroups through inotify. The kernel signals a file
+/// modification whenever `populated` or `frozen` changes.
+///
+/// The watcher can be used on its own through [`EventWatcher::wait`], or its file descriptor can
+/// be registered with poll/epoll; it becomes readable when events are pending.
+pub struct EventWatcher {
+    fd: OwnedFd,
+    watches: HashMap<i32, Watched>,
+}
+
+impl EventWatcher {
+    pub fn new() -> Result<EventWatcher> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(last_os_error());
+        }
+        Ok(EventWatcher {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            watches: HashMap::new(),
+        })
+    }
+
+    /// Start watching `c_group` and return its current state.
+    pub fn add(&mut self, c_group: &CGroup) -> Result<CGroupEvent> {
+        let path = c_group.path().join("cgroup.events");
+        let c_path = c_string(path.as_os_str().as_bytes())?;
+        let mask = libc::IN_MODIFY | libc::IN_DELETE_SELF;
+        let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), c_path.as_ptr(), mask) };
+        if wd < 0 {
+            return Err(last_os_error());
+        }
+        // read after the watch is in place, so that no change can slip through in between
+        let last = match c_group.events() {
+            Ok(last) => last,
+            Err(err) => {
+                // the kernel hands out the same descriptor again for a file that is already watched
+                if !self.watches.contains_key(&wd) {
+                    unsafe { libc::inotify_rm_watch(self.fd.as_raw_fd(), wd) };
+                }
+                return Err(err);
+            }
+        };
+        self.watches.insert(wd, Watched {
+            c_group: c_group.clone(),
+            last: last.clone(),
+        });
+        Ok(last)
+    }
+
+    /// Stop watching `c_group`.
+    pub fn remove(&mut self, c_group: &CGroup) -> Result<()> {
+        let wd = self.watches
+            .iter()
+            .find(|(_, w)| &w.c_group == c_group)
+            .map(|(wd, _)| *wd);
+        if let Some(wd) = wd {
+            self.watches.remove(&wd);
+            if unsafe { libc::inotify_rm_watch(self.fd.as_raw_fd(), wd) } != 0 {
+                return Err(last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+
+    /// Block until at least one watched cgroup changes state and return the transitions. `None`
+    /// waits forever; an empty vector is returned if `timeout` expires first.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<Vec<EventTransition>> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            let transitions = self.read_transitions()?;
+            if !transitions.is_empty() {
+                return Ok(transitions);
+            }
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                return Ok(transitions);
+            }
+            let mut pfd = libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { libc::poll(&mut pfd, 1, poll_timeout_ms(deadline)) } < 0 {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != ErrorKind::Interrupted {
+                    return Err(CGroupError::FSErr(err.kind()));
+                }
+            }
+        }
+    }
+
+    /// Drain the inotify queue without blocking and turn it into state transitions.
+    fn read_transitions(&mut self) -> Result<Vec<EventTransition>> {
+        let mut changed = Vec::new();
+        let mut removed = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = unsafe {
+                libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len())
+            };
+            if n < 0 {
+                let err = std::io::Error::last_os_error();
+                match err.kind() {
+                    ErrorKind::WouldBlock => break,
+                    ErrorKind::Interrupted => continue,
+                    kind => return Err(CGroupError::FSErr(kind)),
+                }
+            }
+            let n = n as usize;
+            let mut offset = 0;
+            while offset + EVENT_HEADER_SIZE <= n {
+                let event = unsafe {
+                    std::ptr::read_unaligned(buf[offset..].as_ptr() as *const libc::inotify_event)
+                };
+                if event.mask & (libc::IN_IGNORED | libc::IN_DELETE_SELF) != 0 {
+                    removed.push(event.wd);
+                } else if !changed.contains(&event.wd) {
+                    changed.push(event.wd);
+                }
+                offset += EVENT_HEADER_SIZE + event.len as usize;
+            }
+        }
+
+        let mut transitions = Vec::new();
+        for wd in changed {
+            if removed.contains(&wd) {
+                continue;
+            }
+            if let Some(watched) = self.watches.get_mut(&wd) {
+                let new = match watched.c_group.events() {
+                    Ok(new) => new,
+                    Err(CGroupError::FSErr(ErrorKind::NotFound)) => {
+                        removed.push(wd);
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                };
+                if new != watched.last {
+                    transitions.push(EventTransition {
+                        c_group: watched.c_group.clone(),
+                        old: std::mem::replace(&mut watched.last, new.clone()),
+                        new: Some(new),
+                    });
+                }
+            }
+        }
+        for wd in removed {
+            if let Some(watched) = self.watches.remove(&wd) {
+                transitions.push(EventTransition {
+                    c_group: watched.c_group,
+                    old: watched.last,
+                    new: None,
+                });
+            }
+        }
+        Ok(transitions)
+    }
+}
+
+impl AsRawFd for EventWatcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, thread, time::Duration};
+
+    use super::EventWatcher;
+    use crate::cgroup::{CGroup, CGroupEvent};
+
+    #[test]
+    fn watch_transitions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("cgroup");
+        fs::create_dir(&dir).unwrap();
+        let events = dir.join("cgroup.events");
+        fs::write(&events, "populated 1\nfrozen 0\n").unwrap();
+
+        let c_group = CGroup::new(dir.as_path());
+        let mut watcher = EventWatcher::new().unwrap();
+        let state = watcher.add(&c_group).unwrap();
+        assert_eq!(state, CGroupEvent { populated: true, frozen: false });
+        assert_eq!(watcher.wait(Some(Duration::from_millis(10))), Ok(vec![]));
+
+        let writer = {
+            let events = events.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                fs::write(&events, "populated 0\nfrozen 0\n").unwrap();
+            })
+        };
+        let transitions = watcher.wait(Some(Duration::from_secs(5))).unwrap();
+        writer.join().unwrap();
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].c_group, c_group);
+        assert!(transitions[0].old.populated);
+        assert_eq!(transitions[0].new, Some(CGroupEvent { populated: false, frozen: false }));
+
+        fs::remove_dir_all(&dir).unwrap();
+        let transitions = watcher.wait(Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].new, None);
+        assert!(watcher.is_empty());
+    }
+}